@@ -20,6 +20,7 @@ extern crate pulse;
 
 use std::sync::mpsc::channel;
 use std::sync::Mutex;
+use std::thread;
 use test::Bencher;
 use pulse::*;
 
@@ -42,6 +43,48 @@ fn pulse_create_and_set(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn pulse_create_and_set_spin(b: &mut Bencher) {
+    // `Signal::wait` never reaches the scheduler for a signal that is
+    // already pulsed, so call it directly to measure the spin path.
+    let sched = SpinScheduler::new(100);
+    b.iter(|| {
+        let (p, t)  = Signal::new();
+        t.pulse();
+        sched.wait(p).unwrap();
+    });
+}
+
+fn ping_pong(b: &mut Bencher, sched: fn() -> Box<dyn Scheduler>) {
+    let (tx, rx) = channel::<Pulse>();
+    let remote = thread::spawn(move || {
+        for t in rx.iter() {
+            t.pulse();
+        }
+    });
+
+    let old = swap_scheduler(sched());
+    b.iter(|| {
+        let (p, t)  = Signal::new();
+        tx.send(t).unwrap();
+        p.wait().unwrap();
+    });
+    old.map(swap_scheduler);
+
+    drop(tx);
+    remote.join().unwrap();
+}
+
+#[bench]
+fn pulse_cross_thread(b: &mut Bencher) {
+    ping_pong(b, || Box::new(ThreadScheduler));
+}
+
+#[bench]
+fn pulse_cross_thread_spin(b: &mut Bencher) {
+    ping_pong(b, || Box::new(SpinScheduler::new(100)));
+}
+
 /*#[bench]
 fn pulse_set(b: &mut Bencher) {
     let (mut p, _) = Signal::new();
@@ -0,0 +1,70 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! The shared parts of the threads that pulse signals in the background,
+//! like the timer and the reactor.
+//!
+//! These threads always fire their pulses with `fire` after releasing
+//! their own locks. Pulsing can run callbacks and wake other threads,
+//! and either may immediately want to register new work with the same
+//! background thread.
+
+use std::sync::{Once, OnceLock};
+use std::thread;
+
+use Pulse;
+
+/// A value shared with a background thread that is started the first
+/// time the value is used.
+pub(crate) struct Background<T> {
+    value: OnceLock<T>,
+    start: Once,
+}
+
+impl<T: Send + Sync + 'static> Background<T> {
+    pub(crate) const fn new() -> Background<T> {
+        Background {
+            value: OnceLock::new(),
+            start: Once::new(),
+        }
+    }
+
+    /// Get the value, creating it with `init` and starting a thread
+    /// named `name` that calls `run` with it if this is the first use.
+    pub(crate) fn get<F>(&'static self, name: &str, init: F, run: fn(&'static T)) -> &'static T
+        where F: FnOnce() -> T
+    {
+        let value = self.value.get_or_init(init);
+        self.start.call_once(|| {
+            thread::Builder::new()
+                .name(name.into())
+                .spawn(move || run(value))
+                .expect("failed to start a pulse background thread");
+        });
+        value
+    }
+}
+
+/// Pulse everything that is still being listened to, this must not be
+/// called while holding a lock that registering new work takes.
+pub(crate) fn fire<I>(pulses: I)
+    where I: IntoIterator<Item = Pulse>
+{
+    for pulse in pulses {
+        if !pulse.is_abandoned() {
+            pulse.pulse();
+        }
+    }
+}
@@ -12,8 +12,9 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use alloc::sync::Arc;
+
+use sync::{AtomicUsize, Mutex, Ordering};
 
 use {Pulse, Signal, Waiting, Signals};
 
@@ -28,7 +29,9 @@ pub struct Barrier {
     inner: Arc<Inner>,
 }
 
-pub struct Handle(pub Arc<Inner>);
+/// The waiter of one of a `Barrier`'s signals, the `Signal` keeps
+/// listening to the pulse until the barrier has been woken.
+pub struct Handle(pub Arc<Inner>, pub Signal);
 
 impl Barrier {
     /// Create a new Barrier from an Vector of `Siganl`s
@@ -40,7 +43,7 @@ impl Barrier {
         });
 
         for pulse in pulses {
-            pulse.clone().arm(Waiting::barrier(Handle(inner.clone())));
+            pulse.add_to_waitlist(Waiting::barrier(Handle(inner.clone(), pulse.clone())));
         }
 
         Barrier { inner: inner }
@@ -51,8 +54,8 @@ impl Signals for Barrier {
     fn signal(&self) -> Signal {
         let (p, t) = Signal::new();
 
-        let mut guard = self.inner.trigger.lock().unwrap();
-        if self.inner.count.load(Ordering::Relaxed) == 0 {
+        let mut guard = self.inner.trigger.lock();
+        if self.inner.count.load(Ordering::Acquire) == 0 {
             t.pulse();
         } else {
             *guard = Some(t);
@@ -0,0 +1,74 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Deadlock detection for `Signal::wait`, enabled by the
+//! `deadlock-detection` feature.
+//!
+//! This keeps a wait-for graph of which thread has claimed which
+//! `Pulse` and which signal every thread is blocked on. A thread that
+//! is about to block follows the graph from the owner of the signal it
+//! waits on, if that leads back to itself it would never be woken.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread::{self, ThreadId};
+
+use {SignalId, WaitError};
+
+#[derive(Default)]
+struct Graph {
+    owners: HashMap<SignalId, ThreadId>,
+    waiting: HashMap<ThreadId, SignalId>,
+}
+
+fn graph() -> MutexGuard<'static, Graph> {
+    static GRAPH: OnceLock<Mutex<Graph>> = OnceLock::new();
+    GRAPH.get_or_init(Default::default).lock().unwrap()
+}
+
+pub fn claim(id: SignalId) {
+    graph().owners.insert(id, thread::current().id());
+}
+
+pub fn release(id: SignalId) {
+    graph().owners.remove(&id);
+}
+
+/// Run `wait` with the current thread recorded as waiting on `id`, or
+/// fail if that would close a cycle in the graph.
+pub fn wait<F>(id: SignalId, wait: F) -> Result<(), WaitError>
+    where F: FnOnce() -> Result<(), WaitError>
+{
+    let me = thread::current().id();
+    {
+        let mut graph = graph();
+        // Every thread checks before it is added, so the graph never
+        // has a cycle and this walk ends.
+        let mut signal = id;
+        while let Some(&owner) = graph.owners.get(&signal) {
+            if owner == me {
+                return Err(WaitError::Deadlock);
+            }
+            match graph.waiting.get(&owner) {
+                Some(&next) => signal = next,
+                None => break,
+            }
+        }
+        graph.waiting.insert(me, id);
+    }
+
+    let res = wait();
+    graph().waiting.remove(&me);
+    res
+}
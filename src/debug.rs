@@ -0,0 +1,139 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! A registry of every live signal, for finding out what a hung
+//! program is waiting on. This is enabled by the `debug-registry`
+//! feature and makes creating and freeing a signal take a global lock.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::Location;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use sync::Ordering;
+use {Inner, SignalId, SignalState, Wake, Waiting};
+
+struct Entry {
+    inner: *const Inner,
+    name: Option<&'static str>,
+    location: &'static Location<'static>,
+    created: Instant,
+}
+
+// `inner` is only read while holding the registry's lock, and it is
+// removed from the registry before it is freed.
+unsafe impl Send for Entry {}
+
+static REGISTRY: Mutex<BTreeMap<SignalId, Entry>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn register(inner: *const Inner,
+                       name: Option<&'static str>,
+                       location: &'static Location<'static>) {
+    let entry = Entry {
+        inner,
+        name,
+        location,
+        created: Instant::now(),
+    };
+    let id = unsafe { (*inner).id };
+    REGISTRY.lock().unwrap().insert(id, entry);
+}
+
+pub(crate) fn unregister(id: SignalId) {
+    REGISTRY.lock().unwrap().remove(&id);
+}
+
+/// What is waiting on a signal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaiterKind {
+    /// A thread blocked by `ThreadScheduler`
+    Thread,
+    /// A `Select` or `SelectMap`
+    Select,
+    /// A `Barrier`
+    Barrier,
+    /// A callback from `Signal::callback`
+    Callback,
+    /// An `Unpark` from `Signal::register`
+    Custom,
+}
+
+/// A pending signal, as reported by `dump`
+#[derive(Clone, Debug)]
+pub struct SignalInfo {
+    /// The id of the signal
+    pub id: SignalId,
+    /// The name given to `Signal::named`
+    pub name: Option<&'static str>,
+    /// Where the signal was created
+    pub location: &'static Location<'static>,
+    /// How long ago the signal was created
+    pub age: Duration,
+    /// Everything that is waiting on the signal
+    pub waiters: Vec<WaiterKind>,
+}
+
+impl fmt::Display for SignalInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{:?}", self.id)?;
+        if let Some(name) = self.name {
+            write!(f, " {:?}", name)?;
+        }
+        write!(f,
+               " created at {} {:?} ago, waiters: {:?}",
+               self.location,
+               self.age,
+               self.waiters)
+    }
+}
+
+fn kind(waiter: &Waiting) -> Option<WaiterKind> {
+    // The waiter is on a waitlist we hold the lock of, so nobody can
+    // be taking the wake out from under us.
+    match unsafe { &*waiter.wake.get() } {
+        None => None,
+        Some(Wake::Thread(_)) => Some(WaiterKind::Thread),
+        Some(Wake::Select(_)) => Some(WaiterKind::Select),
+        Some(Wake::Barrier(_)) => Some(WaiterKind::Barrier),
+        Some(Wake::Callback(_)) => Some(WaiterKind::Callback),
+        Some(Wake::Custom(_)) => Some(WaiterKind::Custom),
+    }
+}
+
+/// List every signal that is still pending, oldest first
+pub fn dump() -> Vec<SignalInfo> {
+    let now = Instant::now();
+    let registry = REGISTRY.lock().unwrap();
+    // ids are handed out in order, so this is already oldest first
+    registry.iter()
+        .filter_map(|(&id, entry)| {
+            let inner = unsafe { &*entry.inner };
+            let state = inner.signal_state(inner.state.load(Ordering::Acquire));
+            if state != SignalState::Pending {
+                return None;
+            }
+
+            let mut waiters = Vec::new();
+            inner.waiting.lock().for_each(|w| waiters.extend(kind(w)));
+            Some(SignalInfo {
+                id,
+                name: entry.name,
+                location: entry.location,
+                age: now.saturating_duration_since(entry.created),
+                waiters,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! A `Signal` that can be polled as a file descriptor on Linux.
+
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::Arc;
+
+use libc;
+
+use {Registration, Signal, Unpark};
+
+/// A non-blocking eventfd, unparking it makes it readable. It is
+/// closed once the last reference is dropped.
+pub(crate) struct EventFd(RawFd);
+
+impl EventFd {
+    pub(crate) fn new() -> io::Result<EventFd> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(EventFd(fd))
+    }
+
+    /// Make the eventfd unreadable again
+    pub(crate) fn reset(&self) {
+        let mut count: u64 = 0;
+        unsafe {
+            libc::read(self.0, &mut count as *mut u64 as *mut libc::c_void, 8);
+        }
+    }
+}
+
+impl AsRawFd for EventFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Unpark for EventFd {
+    fn unpark(&self) {
+        let one: u64 = 1;
+        // This can only fail if the counter would overflow
+        unsafe {
+            libc::write(self.0, &one as *const u64 as *const libc::c_void, 8);
+        }
+    }
+}
+
+impl Drop for EventFd {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0) };
+    }
+}
+
+/// A `Signal` paired with an eventfd that becomes readable once the
+/// signal is no longer pending, for use with `epoll` or `poll`.
+///
+/// The eventfd stays readable from then on, like the signal it is
+/// never reset. Do not read from it, or it will stop being readable.
+pub struct FdSignal {
+    signal: Signal,
+    event: Arc<EventFd>,
+    registration: Option<Registration>,
+}
+
+impl FdSignal {
+    /// Create an eventfd for `signal`. It is non-blocking and is
+    /// closed on exec.
+    pub fn new(signal: Signal) -> io::Result<FdSignal> {
+        let event = Arc::new(EventFd::new()?);
+        let registration = signal.register(event.clone());
+        Ok(FdSignal {
+            signal,
+            event,
+            registration: Some(registration),
+        })
+    }
+}
+
+impl Deref for FdSignal {
+    type Target = Signal;
+
+    fn deref(&self) -> &Signal {
+        &self.signal
+    }
+}
+
+impl fmt::Debug for FdSignal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "FdSignal({:?}, fd {})", self.signal.id(), self.event.0)
+    }
+}
+
+impl AsRawFd for FdSignal {
+    fn as_raw_fd(&self) -> RawFd {
+        self.event.0
+    }
+}
+
+impl Drop for FdSignal {
+    fn drop(&mut self) {
+        if let Some(registration) = self.registration.take() {
+            self.signal.unregister(registration);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::boxed::Box;
+
 pub trait FnBox {
     fn call_box(self: Box<Self>);
 }
@@ -12,124 +12,296 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
-extern crate atom;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Without the default `std` feature only `alloc` is required. There is
+//! no `ThreadScheduler` in that case, so a `Scheduler` must be installed
+//! with `set_scheduler` before anything can `wait`.
 
-use std::sync::atomic::AtomicUsize;
-use std::thread;
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "std")]
+extern crate atom;
+#[cfg(loom)]
+extern crate loom;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(all(unix, feature = "std"))]
+extern crate libc;
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::sync::atomic;
+use core::mem;
+use core::fmt;
+use core::ops::Deref;
+use core::cell::UnsafeCell;
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+#[cfg(feature = "std")]
 use std::time::{Duration, Instant};
-use std::mem;
-use std::fmt;
-use std::ops::Deref;
-use std::sync::atomic::Ordering;
+#[cfg(feature = "std")]
 use std::cell::RefCell;
 
+#[cfg(feature = "std")]
 use atom::*;
 use fnbox::FnBox;
+use sync::{fence, AtomicUsize, Ordering};
+use waitlist::WaitList;
 
 pub use select::{Select, SelectMap};
 pub use barrier::Barrier;
+#[cfg(all(target_os = "linux", feature = "std"))]
+pub use fd::FdSignal;
 mod select;
 mod barrier;
+#[cfg(feature = "std")]
+mod background;
 mod fnbox;
+mod sync;
+mod waitlist;
+#[cfg(feature = "debug-registry")]
+pub mod debug;
+#[cfg(feature = "deadlock-detection")]
+mod deadlock;
+#[cfg(all(target_os = "linux", feature = "std"))]
+mod fd;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(all(unix, feature = "std"))]
+pub mod process;
+#[cfg(all(target_os = "linux", feature = "std"))]
+pub mod reactor;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod thread;
+#[cfg(feature = "std")]
+pub mod timer;
+#[cfg(all(unix, feature = "std"))]
+pub mod unix;
 
 /// Drop rules
 /// This may be freed iff state is Signald | Dropped
 /// and Waiting is Dropped
 struct Inner {
+    id: SignalId,
     state: AtomicUsize,
-    waiting: Atom<Box<Waiting>>,
+    // The count of every `WeakSignal`, plus one that is shared by all
+    // of the references counted in `state`. Like `Arc`, this keeps the
+    // weak count out of the way of the strong one.
+    weak: AtomicUsize,
+    waiting: WaitList,
+    // The `Pulse` of the signal returned by `Pulse::closed`, or zero
+    closed: AtomicUsize,
+    // Why the `Pulse` was dropped, written once before `TX_DROP` is set
+    dropped: UnsafeCell<SignalState>,
+    // When the `Pulse` fired, see `metrics::now`
+    #[cfg(feature = "metrics")]
+    pulsed_at: atomic::AtomicU64,
+    // The name given to `Signal::named`, recorded on every span
+    #[cfg(feature = "tracing")]
+    name: Option<&'static str>,
 }
 
-// TODO 64bit sized, probably does not matter now
-const PULSED: usize = 0x8000_0000;
-const TX_DROP: usize = 0x4000_0000;
-const TX_FLAGS: usize = PULSED | TX_DROP;
-const REF_COUNT: usize = !TX_FLAGS;
+impl Inner {
+    fn new(state: usize) -> Inner {
+        Inner {
+            id: SignalId::next(),
+            state: AtomicUsize::new(state),
+            weak: AtomicUsize::new(1),
+            waiting: WaitList::new(),
+            closed: AtomicUsize::new(0),
+            dropped: UnsafeCell::new(SignalState::Dropped),
+            #[cfg(feature = "metrics")]
+            pulsed_at: atomic::AtomicU64::new(0),
+            #[cfg(feature = "tracing")]
+            name: None,
+        }
+    }
 
-struct Waiting {
-    next: Option<Box<Waiting>>,
-    wake: Wake,
+    /// The `SignalState` of a state word that was loaded with `Acquire`
+    #[inline]
+    fn signal_state(&self, flags: usize) -> SignalState {
+        match (flags & TX_DROP == TX_DROP, flags & PULSED == PULSED) {
+            (_, true) => SignalState::Pulsed,
+            (true, _) => unsafe { *self.dropped.get() },
+            (_, _) => SignalState::Pending,
+        }
+    }
+
+    fn take_closed(&self) -> Option<Pulse> {
+        match self.closed.swap(0, Ordering::AcqRel) {
+            0 => None,
+            pulse => Some(unsafe { Pulse::cast_from_usize(pulse) }),
+        }
+    }
 }
 
-impl GetNextMut for Box<Waiting> {
-    type NextPtr = Option<Box<Waiting>>;
+/// A unique identifier of a `Signal` and its `Pulse`, every clone of a
+/// `Signal` shares the same id. Unlike an address an id is never reused,
+/// even after the signal it was handed out for has been freed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SignalId(u64);
+
+#[cfg(target_has_atomic = "64")]
+impl SignalId {
+    fn next() -> SignalId {
+        // Only uniqueness matters, so this is left out of the loom model.
+        static NEXT: atomic::AtomicU64 = atomic::AtomicU64::new(0);
+        SignalId(NEXT.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+}
 
-    fn get_next(&mut self) -> &mut Option<Box<Waiting>> {
-        &mut self.next
+/// Without 64 bit atomics the ids are counted in a `usize`, so on a 32
+/// bit target they are reused after 2^32 signals.
+#[cfg(not(target_has_atomic = "64"))]
+impl SignalId {
+    fn next() -> SignalId {
+        static NEXT: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+        SignalId(NEXT.fetch_add(1, atomic::Ordering::Relaxed) as u64)
     }
 }
 
+// The flags live in the top three bits of the state word, the rest of
+// the word is the count of the `Pulse` and every `Signal`.
+const PULSED: usize = 1 << (usize::BITS - 1);
+const TX_DROP: usize = 1 << (usize::BITS - 2);
+const TX_FLAGS: usize = PULSED | TX_DROP;
+// Set once `Pulse::closed` has stored a pulse in `Inner::closed`
+const CLOSED: usize = 1 << (usize::BITS - 3);
+const REF_COUNT: usize = CLOSED - 1;
+
+// Like `Arc`, cloning a `Signal` aborts long before the count could
+// reach the flags. Getting this far requires leaking clones, so there
+// is plenty of headroom for any threads racing to increment it.
+const MAX_REF_COUNT: usize = max_ref_count(usize::BITS) as usize;
+
+/// `MAX_REF_COUNT` for a `usize` of `bits`, this is also the limit
+/// of the weak count.
+const fn max_ref_count(bits: u32) -> u64 {
+    (1 << (bits - 4)) - 1
+}
+
+struct Waiting {
+    link: waitlist::Link,
+    wake: UnsafeCell<Option<Wake>>,
+}
+
+// The links are only touched while holding the waitlist's lock, and
+// `wake` only by whoever took the waiter off of the waitlist.
+unsafe impl Send for Waiting {}
+unsafe impl Sync for Waiting {}
+
 enum Wake {
-    Thread(thread::Thread),
+    #[cfg(feature = "std")]
+    Thread(sync::Thread),
     Select(select::Handle),
     Barrier(barrier::Handle),
     Callback(Box<FnBox>),
+    Custom(Arc<dyn Unpark>),
 }
 
 impl Waiting {
-    fn wake(s: Box<Self>, id: usize) {
-        let mut next = Some(s);
-        while let Some(s) = next {
-            // There must be a better way to do this...
-            let s = *s;
-            let Waiting { next: n, wake } = s;
-            next = n;
-            match wake {
-                Wake::Thread(thread) => thread.unpark(),
-                Wake::Select(select) => {
-                    let trigger = {
-                        let mut guard = select.0.lock().unwrap();
-                        guard.ready.push(id);
-                        guard.trigger.take()
-                    };
-                    trigger.map(|x| x.pulse());
-                }
-                Wake::Barrier(barrier) => {
-                    let count = barrier.0.count.fetch_sub(1, Ordering::Relaxed);
-                    if count == 1 {
-                        let mut guard = barrier.0.trigger.lock().unwrap();
-                        if let Some(t) = guard.take() {
-                            t.pulse();
-                        }
+    /// Wake the waiter, this must only be called by whoever took it
+    /// off of the waitlist, or by whoever never put it on one.
+    fn wake(&self) {
+        let wake = unsafe { (*self.wake.get()).take() };
+        #[cfg(feature = "metrics")]
+        {
+            if wake.is_some() {
+                metrics::woke();
+            }
+        }
+        match wake {
+            None => (),
+            #[cfg(feature = "std")]
+            Some(Wake::Thread(thread)) => thread.unpark(),
+            Some(Wake::Select(select)) => {
+                let trigger = {
+                    let mut guard = select.0.lock();
+                    guard.ready.push(select.1);
+                    guard.trigger.take()
+                };
+                trigger.map(|x| x.pulse());
+            }
+            Some(Wake::Barrier(barrier)) => {
+                let count = barrier.0.count.fetch_sub(1, Ordering::AcqRel);
+                if count == 1 {
+                    let mut guard = barrier.0.trigger.lock();
+                    if let Some(t) = guard.take() {
+                        t.pulse();
                     }
                 }
-                Wake::Callback(cb) => cb.call_box(),
+                drop(barrier.1);
             }
+            Some(Wake::Callback(cb)) => cb.call_box(),
+            Some(Wake::Custom(unpark)) => unpark.unpark(),
         }
     }
 
-    fn id(&self) -> usize {
-        unsafe { mem::transmute(self) }
+    fn new(wake: Wake) -> Arc<Waiting> {
+        Arc::new(Waiting {
+            link: waitlist::Link::new(),
+            wake: UnsafeCell::new(Some(wake)),
+        })
     }
 
-    fn thread() -> Box<Waiting> {
-        Box::new(Waiting {
-            next: None,
-            wake: Wake::Thread(thread::current()),
-        })
+    #[cfg(feature = "std")]
+    fn thread() -> Arc<Waiting> {
+        Waiting::new(Wake::Thread(sync::current()))
     }
 
-    fn select(handle: select::Handle) -> Box<Waiting> {
-        Box::new(Waiting {
-            next: None,
-            wake: Wake::Select(handle),
-        })
+    fn select(handle: select::Handle) -> Arc<Waiting> {
+        Waiting::new(Wake::Select(handle))
     }
 
-    fn barrier(handle: barrier::Handle) -> Box<Waiting> {
-        Box::new(Waiting {
-            next: None,
-            wake: Wake::Barrier(handle),
-        })
+    fn barrier(handle: barrier::Handle) -> Arc<Waiting> {
+        Waiting::new(Wake::Barrier(handle))
     }
 
-    fn callback<F>(cb: F) -> Box<Waiting>
+    fn callback<F>(cb: F) -> Arc<Waiting>
         where F: FnOnce() + 'static
     {
-        Box::new(Waiting {
-            next: None,
-            wake: Wake::Callback(Box::new(cb)),
-        })
+        Waiting::new(Wake::Callback(Box::new(cb)))
+    }
+
+    fn custom(unpark: Arc<dyn Unpark>) -> Arc<Waiting> {
+        Waiting::new(Wake::Custom(unpark))
+    }
+}
+
+/// Something that can be woken when a `Signal` it is waiting on
+/// leaves the pending state. A `Scheduler` that does not block an
+/// OS thread, like a fiber or green thread scheduler, implements this
+/// to resume the task that is waiting.
+pub trait Unpark: Send + Sync {
+    /// Wake the waiting task. This may be called from any thread,
+    /// and may be called after the task has already stopped waiting.
+    fn unpark(&self);
+}
+
+#[cfg(feature = "std")]
+impl Unpark for std::thread::Thread {
+    fn unpark(&self) {
+        std::thread::Thread::unpark(self)
+    }
+}
+
+/// A registration of an `Unpark` on a `Signal`'s waitlist, used to
+/// remove it again with `Signal::unregister`.
+pub struct Registration {
+    waiter: Arc<Waiting>,
+    // The signal whose waitlist `waiter` was pushed onto
+    id: SignalId,
+}
+
+impl fmt::Debug for Registration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "Registration({:p})", self.waiter)
     }
 }
 
@@ -148,24 +320,49 @@ pub struct Pulse {
 
 impl fmt::Debug for Pulse {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        let id: usize = unsafe { mem::transmute(self.inner) };
-        write!(f, "Pulse({:?})", id)
+        write!(f, "Pulse({:?})", self.inner().id)
     }
 }
 
-fn delete_inner(state: usize, inner: *mut Inner) {
+/// Release the weak reference shared by the strong ones if `state` was
+/// read by the release of the last strong reference.
+fn release_strong(state: usize, inner: *mut Inner) {
     if state & REF_COUNT == 1 {
+        // The strong releases are `Release`, this orders all of them
+        // before the weak release that may free `inner`.
+        fence(Ordering::Acquire);
+        release_weak(inner);
+    }
+}
+
+/// Release a weak reference, freeing `inner` if it was the last one
+fn release_weak(inner: *mut Inner) {
+    if unsafe { &*inner }.weak.fetch_sub(1, Ordering::Release) == 1 {
+        // Every release of a reference is a `Release`, this synchronizes
+        // with all of them so their use of `inner` happens before the free.
+        fence(Ordering::Acquire);
         let inner: Box<Inner> = unsafe { mem::transmute(inner) };
+        #[cfg(feature = "debug-registry")]
+        debug::unregister(inner.id);
         drop(inner);
     }
 }
 
+/// Take a weak reference, aborting if there are far too many
+fn acquire_weak(inner: &Inner) {
+    if inner.weak.fetch_add(1, Ordering::Relaxed) > MAX_REF_COUNT {
+        sync::abort();
+    }
+}
+
 impl Drop for Pulse {
     fn drop(&mut self) {
-        self.set(TX_DROP);
-        self.wake();
-        let state = self.inner().state.fetch_sub(1, Ordering::Relaxed);
-        delete_inner(state, self.inner)
+        if sync::panicking() {
+            self.drop_as(SignalState::Panicked);
+        } else {
+            self.drop_as(SignalState::Dropped);
+        }
+        self.release();
     }
 }
 
@@ -192,29 +389,141 @@ impl Pulse {
 
     #[inline]
     fn set(&self, state: usize) -> usize {
-        self.inner().state.fetch_or(state, Ordering::Relaxed)
+        self.inner().state.fetch_or(state, Ordering::Release)
+    }
+
+    /// Check if every `Signal` listening to this pulse has been dropped,
+    /// and no callback or `Barrier` is waiting on it, in which case
+    /// nobody will observe the pulse.
+    #[inline]
+    pub fn is_abandoned(&self) -> bool {
+        self.inner().state.load(Ordering::Relaxed) & REF_COUNT == 1
+    }
+
+    /// The number of `Signal`s that are listening to this pulse, a
+    /// callback or `Barrier` that is waiting on it counts as one.
+    #[inline]
+    pub fn waiter_count(&self) -> usize {
+        (self.inner().state.load(Ordering::Relaxed) & REF_COUNT) - 1
+    }
+
+    /// Get a `Signal` that is pulsed once this pulse is abandoned, see
+    /// `is_abandoned`, letting the producer give up early. If this
+    /// pulse fires or is dropped first, the returned signal is dropped.
+    ///
+    /// A `WeakSignal` that is upgraded after the last `Signal` was
+    /// dropped does not undo this.
+    pub fn closed(&mut self) -> Signal {
+        if self.is_abandoned() {
+            return Signal::pulsed();
+        }
+
+        let (signal, pulse) = match self.inner().take_closed() {
+            Some(pulse) => (pulse.signal(), pulse),
+            None => Signal::new(),
+        };
+        let pulse = unsafe { pulse.cast_to_usize() };
+        self.inner().closed.store(pulse, Ordering::Release);
+
+        // If the last `Signal` was dropped before the flag was set it
+        // did not look for the closed signal, so it is up to us.
+        let state = self.inner().state.fetch_or(CLOSED, Ordering::AcqRel);
+        if state & REF_COUNT == 1 {
+            if let Some(pulse) = self.inner().take_closed() {
+                pulse.pulse();
+            }
+        }
+        signal
+    }
+
+    /// Create another `Signal` that listens to this pulse
+    fn signal(&self) -> Signal {
+        let state = self.inner().state.fetch_add(1, Ordering::Relaxed);
+        if state & REF_COUNT > MAX_REF_COUNT {
+            sync::abort();
+        }
+        Signal { inner: self.inner }
     }
 
     #[inline]
     fn wake(&self) {
-        let id = unsafe { mem::transmute(self.inner) };
-        match self.inner().waiting.take() {
-            None => (),
-            Some(v) => Waiting::wake(v, id),
+        let waiting = self.inner().waiting.lock().take();
+        for waiter in waiting {
+            waiter.wake();
         }
+        drop(self.inner().take_closed());
     }
 
     /// Pulse the `pulse` which will transition the `Signal` out from pending
     /// to ready. This moves the pulse so that it can only be fired once.
+    ///
+    /// This is a release operation, everything written before the pulse
+    /// is visible to any thread that observes the `Signal` as `Pulsed`.
     #[inline]
     pub fn pulse(self) {
+        #[cfg(feature = "metrics")]
+        {
+            self.inner().pulsed_at.store(metrics::now(), atomic::Ordering::Relaxed);
+            metrics::pulsed();
+        }
+        #[cfg(feature = "tracing")]
+        trace::pulsed(self.inner());
         self.set(PULSED);
         self.wake();
+        self.release();
+        mem::forget(self)
+    }
+
+    /// Drop the pulse because it failed, the `Signal` will be in the
+    /// `Failed(code)` state.
+    pub fn fail(self, code: u32) {
+        self.drop_as(SignalState::Failed(code));
+        self.release();
+        mem::forget(self)
+    }
 
-        let state = self.inner().state.fetch_sub(1, Ordering::Relaxed);
-        delete_inner(state, self.inner);
+    /// Drop the pulse because the work it stood for was cancelled, the
+    /// `Signal` will be in the `Cancelled` state.
+    pub fn cancel(self) {
+        self.drop_as(SignalState::Cancelled);
+        self.release();
         mem::forget(self)
     }
+
+    /// Set `TX_DROP` with `state` as the reason, this must only be
+    /// called once.
+    #[inline]
+    fn drop_as(&self, state: SignalState) {
+        #[cfg(feature = "metrics")]
+        metrics::dropped();
+        #[cfg(feature = "tracing")]
+        trace::dropped(self.inner(), state);
+        unsafe { *self.inner().dropped.get() = state };
+        self.set(TX_DROP);
+        self.wake();
+    }
+
+    /// Record the current thread as the owner of this pulse, call this
+    /// again after moving the pulse to another thread.
+    ///
+    /// With the `deadlock-detection` feature, a `Signal::wait` that
+    /// would block the owner on its own pulse, either directly or
+    /// through other threads that are waiting on pulses the waiting
+    /// thread owns, returns `WaitError::Deadlock` instead. Without the
+    /// feature this does nothing.
+    pub fn claim(&self) {
+        #[cfg(feature = "deadlock-detection")]
+        deadlock::claim(self.inner().id);
+    }
+
+    /// Release the pulse's reference to `inner`
+    #[inline]
+    fn release(&self) {
+        #[cfg(feature = "deadlock-detection")]
+        deadlock::release(self.inner().id);
+        let state = self.inner().state.fetch_sub(1, Ordering::Release);
+        release_strong(state, self.inner);
+    }
 }
 
 
@@ -244,7 +553,10 @@ impl fmt::Debug for Signal {
 impl Clone for Signal {
     #[inline(always)]
     fn clone(&self) -> Signal {
-        self.inner().state.fetch_add(1, Ordering::Relaxed);
+        let state = self.inner().state.fetch_add(1, Ordering::Relaxed);
+        if state & REF_COUNT > MAX_REF_COUNT {
+            sync::abort();
+        }
         Signal { inner: self.inner }
     }
 }
@@ -252,30 +564,84 @@ impl Clone for Signal {
 impl Drop for Signal {
     #[inline]
     fn drop(&mut self) {
-        let flag = self.inner().state.fetch_sub(1, Ordering::Relaxed);
-        delete_inner(flag, self.inner);
+        let mut state = self.inner().state.load(Ordering::Relaxed);
+        let mut weak = false;
+        loop {
+            // The last `Signal` trades its reference for a weak one, so
+            // `inner` can not be freed by the `Pulse` while it pulses the
+            // `Pulse::closed` signal. The weak reference is taken first,
+            // it is only given back if the swap does not happen.
+            let closing = state & CLOSED != 0 && state & TX_FLAGS == 0 &&
+                          state & REF_COUNT == 2;
+            if closing && !weak {
+                acquire_weak(self.inner());
+                weak = true;
+            }
+            match self.inner().state.compare_exchange_weak(state,
+                                                           state - 1,
+                                                           Ordering::AcqRel,
+                                                           Ordering::Relaxed) {
+                Ok(_) if closing => return self.close(),
+                Ok(_) => {
+                    if weak {
+                        release_weak(self.inner);
+                    }
+                    return release_strong(state, self.inner);
+                }
+                Err(actual) => state = actual,
+            }
+        }
     }
 }
 
 impl Signal {
+    /// Pulse the `Pulse::closed` signal, then release the weak reference
+    /// the last `Signal` was traded for.
+    #[cold]
+    fn close(&mut self) {
+        if let Some(pulse) = self.inner().take_closed() {
+            pulse.pulse();
+        }
+
+        release_weak(self.inner);
+    }
+
     /// Create a Signal and a Pulse that are associated.
+    #[cfg_attr(feature = "debug-registry", track_caller)]
     pub fn new() -> (Signal, Pulse) {
-        let inner = Box::new(Inner {
-            state: AtomicUsize::new(2),
-            waiting: Atom::empty(),
-        });
+        Signal::create(None)
+    }
 
-        let inner = unsafe { mem::transmute(inner) };
+    /// Create a Signal and a Pulse that are associated, `name` is
+    /// shown by `debug::dump` when the `debug-registry` feature is on.
+    #[cfg_attr(feature = "debug-registry", track_caller)]
+    pub fn named(name: &'static str) -> (Signal, Pulse) {
+        Signal::create(Some(name))
+    }
+
+    #[cfg_attr(feature = "debug-registry", track_caller)]
+    fn create(name: Option<&'static str>) -> (Signal, Pulse) {
+        #[allow(unused_mut)]
+        let mut inner = Box::new(Inner::new(2));
+        #[cfg(feature = "tracing")]
+        {
+            inner.name = name;
+            trace::created(&inner);
+        }
+
+        let inner: *mut Inner = unsafe { mem::transmute(inner) };
+
+        #[cfg(feature = "debug-registry")]
+        debug::register(inner, name, core::panic::Location::caller());
+        #[cfg(not(any(feature = "debug-registry", feature = "tracing")))]
+        let _ = name;
 
         (Signal { inner: inner }, Pulse { inner: inner })
     }
 
     /// Create a signal that is already pulsed
     pub fn pulsed() -> Signal {
-        let inner = Box::new(Inner {
-            state: AtomicUsize::new(1 | PULSED),
-            waiting: Atom::empty(),
-        });
+        let inner = Box::new(Inner::new(1 | PULSED));
 
         let inner = unsafe { mem::transmute(inner) };
 
@@ -288,14 +654,13 @@ impl Signal {
     }
 
     /// Read out the state of the Signal
+    ///
+    /// This is an acquire operation, if the state is not `Pending` then
+    /// everything written before the `Pulse` was fired or dropped is
+    /// visible to the caller.
     #[inline]
     pub fn state(&self) -> SignalState {
-        let flags = self.inner().state.load(Ordering::Relaxed);
-        match (flags & TX_DROP == TX_DROP, flags & PULSED == PULSED) {
-            (_, true) => SignalState::Pulsed,
-            (true, _) => SignalState::Dropped,
-            (_, _) => SignalState::Pending,
-        }
+        self.inner().signal_state(self.inner().state.load(Ordering::Acquire))
     }
 
     /// Check to see if the signal is pending. A signal 
@@ -304,94 +669,235 @@ impl Signal {
         self.state() == SignalState::Pending
     }
 
-    /// Add a waiter to a waitlist
-    fn add_to_waitlist(&self, waiter: Box<Waiting>) -> usize {
-        let id = waiter.id();
-
-        if !self.is_pending() {
-            Waiting::wake(waiter, self.id());
-            return id;
-        }
-
-        self.inner().waiting.replace_and_set_next(waiter);
-
-        // if armed fire now
-        if !self.is_pending() {
-            if let Some(t) = self.inner().waiting.take() {
-                Waiting::wake(t, self.id());
+    /// Add a waiter to a waitlist, if the signal is not pending
+    /// the waiter is woken immediately.
+    fn add_to_waitlist(&self, waiter: Arc<Waiting>) -> Registration {
+        {
+            // The state is checked under the lock, so either this will
+            // see the pulse or the pulse will see the waiter.
+            let guard = self.inner().waiting.lock();
+            if self.is_pending() {
+                guard.push(waiter.clone());
+                return Registration { waiter, id: self.id() };
             }
         }
-        id
+
+        waiter.wake();
+        Registration { waiter, id: self.id() }
     }
 
-    /// Remove Waiter with `id` from the waitlist
-    fn remove_from_waitlist(&self, id: usize) {
-        let mut wl = self.inner().waiting.take();
-        while let Some(mut w) = wl {
-            let next = w.next.take();
-            if w.id() != id {
-                self.add_to_waitlist(w);
-            }
-            wl = next;
-        }
+    /// Remove a waiter from the waitlist, this is a no-op if
+    /// it has already been woken.
+    ///
+    /// The waiter's links are only meaningful on the list it was pushed
+    /// onto, unlinking it from any other list would corrupt both.
+    fn remove_from_waitlist(&self, registration: Registration) {
+        assert!(registration.id == self.id(),
+                "a Registration must be removed from the Signal it was registered on");
+        self.inner().waiting.lock().remove(&registration.waiter);
     }
 
     /// Arm a pulse to wake 
-    fn arm(self, waiter: Box<Waiting>) -> ArmedSignal {
-        let id = self.add_to_waitlist(waiter);
+    fn arm(self, waiter: Arc<Waiting>) -> ArmedSignal {
+        let registration = self.add_to_waitlist(waiter);
         ArmedSignal {
-            id: id,
+            registration,
             pulse: self,
         }
     }
 
     /// This is a unique id that can be used to identify the signal from others
     /// See `Select` for how this api is useful.
-    pub fn id(&self) -> usize {
-        unsafe { mem::transmute_copy(&self.inner) }
+    pub fn id(&self) -> SignalId {
+        self.inner().id
+    }
+
+    /// Create a `WeakSignal` that observes this signal without
+    /// keeping it alive.
+    pub fn downgrade(&self) -> WeakSignal {
+        acquire_weak(self.inner());
+        WeakSignal { inner: self.inner }
     }
 
     /// Block the current thread until a `pulse` is ready.
     /// This will block indefinably if the pulse never fires.
+    ///
+    /// Like `state`, returning from a wait acquires everything that was
+    /// written before the `Pulse` was fired.
     #[inline]
     pub fn wait(self) -> Result<(), WaitError> {
-        match self.state() {
-            SignalState::Pulsed => Ok(()),
-            SignalState::Dropped => Err(WaitError::Dropped),
-            SignalState::Pending => {
-                let s = take_scheduler().expect("no scheduler found");
-                let res = s.wait(self);
-                swap_scheduler(s);
-                res
-            }
+        match self.state().result() {
+            Some(res) => res,
+            None => self.block(),
         }
     }
 
+    /// Wait on the current scheduler, the signal is known to be pending
+    fn block(self) -> Result<(), WaitError> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::wait(self.inner());
+        #[cfg(feature = "deadlock-detection")]
+        let res = deadlock::wait(self.id(), || with_current_scheduler(|s| s.wait(self)));
+        #[cfg(not(feature = "deadlock-detection"))]
+        let res = with_current_scheduler(|s| s.wait(self));
+        #[cfg(feature = "tracing")]
+        trace::waited(&res);
+        res
+    }
+
     /// Block until either the pulse is sent, or the timeout is reached
     pub fn wait_timeout_ms(self, ms: u32) -> Result<(), TimeoutError> {
-        SCHED.with(|sched| {
-            let s = sched.borrow_mut().take().expect("Waited while: no scheduler installed");
-            let res = s.wait_timeout_ms(self, ms);
-            *sched.borrow_mut() = Some(s);
-            res
-        })
+        with_current_scheduler(|s| s.wait_timeout_ms(self, ms))
     }
 
     pub fn callback<F>(self, cb: F)
         where F: FnOnce() + 'static
     {
-        self.add_to_waitlist(Waiting::callback(cb));
+        // The callback listens to the pulse until it has run
+        let signal = self.clone();
+        self.add_to_waitlist(Waiting::callback(move || {
+            cb();
+            drop(signal);
+        }));
+    }
+
+    /// Register `unpark` to be woken once the signal is no longer
+    /// pending. If the signal is not pending it is woken immediately.
+    ///
+    /// This is the building block for a `Scheduler` that needs
+    /// to be woken by something other than `thread::unpark`.
+    pub fn register(&self, unpark: Arc<dyn Unpark>) -> Registration {
+        self.add_to_waitlist(Waiting::custom(unpark))
+    }
+
+    /// Remove an `Unpark` that was registered with `register`. This
+    /// is a no-op if it has already been woken.
+    ///
+    /// # Panics
+    ///
+    /// If `registration` was returned by `register` on another signal.
+    pub fn unregister(&self, registration: Registration) {
+        self.remove_from_waitlist(registration);
+    }
+
+    /// Create an eventfd that becomes readable once this signal is no
+    /// longer pending, see `FdSignal`.
+    #[cfg(all(target_os = "linux", feature = "std"))]
+    pub fn as_event_fd(&self) -> std::io::Result<FdSignal> {
+        FdSignal::new(self.clone())
     }
 }
 
 /// Described the possible states of a Signal
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SignalState {
     Pending,
     Pulsed,
+    /// The `Pulse` was dropped without firing
     Dropped,
+    /// The `Pulse` was dropped with `Pulse::cancel`
+    Cancelled,
+    /// The `Pulse` was dropped while its thread was panicking
+    Panicked,
+    /// The `Pulse` was dropped with `Pulse::fail`
+    Failed(u32),
+}
+
+impl SignalState {
+    /// The result of waiting on a signal in this state, or `None`
+    /// if it is still pending.
+    #[inline]
+    fn result(self) -> Option<Result<(), WaitError>> {
+        match self {
+            SignalState::Pending => None,
+            SignalState::Pulsed => Some(Ok(())),
+            SignalState::Dropped => Some(Err(WaitError::Dropped)),
+            SignalState::Cancelled => Some(Err(WaitError::Cancelled)),
+            SignalState::Panicked => Some(Err(WaitError::Panicked)),
+            SignalState::Failed(code) => Some(Err(WaitError::Failed(code))),
+        }
+    }
+}
+
+unsafe impl Send for WeakSignal {}
+unsafe impl Sync for WeakSignal {}
+
+/// A `WeakSignal` is a reference to a `Signal` that does not count as
+/// one of its listeners, it is created with `Signal::downgrade`. Once
+/// the `Pulse` and every `Signal` are gone the `WeakSignal` can no
+/// longer be upgraded.
+pub struct WeakSignal {
+    inner: *mut Inner,
+}
+
+impl fmt::Debug for WeakSignal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "WeakSignal(id={:?}, state={:?})", self.id(), self.state())
+    }
+}
+
+impl Clone for WeakSignal {
+    fn clone(&self) -> WeakSignal {
+        acquire_weak(self.inner());
+        WeakSignal { inner: self.inner }
+    }
+}
+
+impl Drop for WeakSignal {
+    fn drop(&mut self) {
+        release_weak(self.inner);
+    }
 }
 
+impl WeakSignal {
+    #[inline]
+    fn inner(&self) -> &Inner {
+        unsafe { &*self.inner }
+    }
+
+    /// Get a `Signal` back, this fails if the `Pulse` and every
+    /// `Signal` have already been dropped.
+    pub fn upgrade(&self) -> Option<Signal> {
+        let mut state = self.inner().state.load(Ordering::Relaxed);
+        loop {
+            // Once the count reaches zero it can never be raised again
+            if state & REF_COUNT == 0 {
+                return None;
+            }
+            if state & REF_COUNT > MAX_REF_COUNT {
+                sync::abort();
+            }
+            match self.inner().state.compare_exchange_weak(state,
+                                                           state + 1,
+                                                           Ordering::Relaxed,
+                                                           Ordering::Relaxed) {
+                Ok(_) => return Some(Signal { inner: self.inner }),
+                Err(actual) => state = actual,
+            }
+        }
+    }
+
+    /// Read out the state of the `Signal`, this is `None` once it can no
+    /// longer be upgraded.
+    ///
+    /// Like `Signal::state` this is an acquire operation.
+    pub fn state(&self) -> Option<SignalState> {
+        let flags = self.inner().state.load(Ordering::Acquire);
+        if flags & REF_COUNT == 0 {
+            None
+        } else {
+            Some(self.inner().signal_state(flags))
+        }
+    }
+
+    /// The id of the `Signal` this was downgraded from
+    pub fn id(&self) -> SignalId {
+        self.inner().id
+    }
+}
+
+#[cfg(feature = "std")]
 impl IntoRawPtr for Pulse {
     #[inline(always)]
     unsafe fn into_raw(self) -> *mut () {
@@ -401,6 +907,7 @@ impl IntoRawPtr for Pulse {
     }
 }
 
+#[cfg(feature = "std")]
 impl FromRawPtr for Pulse {
     #[inline(always)]
     unsafe fn from_raw(ptr: *mut ()) -> Pulse {
@@ -408,6 +915,7 @@ impl FromRawPtr for Pulse {
     }
 }
 
+#[cfg(feature = "std")]
 impl IntoRawPtr for Signal {
     #[inline(always)]
     unsafe fn into_raw(self) -> *mut () {
@@ -417,6 +925,7 @@ impl IntoRawPtr for Signal {
     }
 }
 
+#[cfg(feature = "std")]
 impl FromRawPtr for Signal {
     #[inline(always)]
     unsafe fn from_raw(ptr: *mut ()) -> Signal {
@@ -426,11 +935,36 @@ impl FromRawPtr for Signal {
 
 /// Represents the possible errors that can occur on a `Signal`
 #[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum WaitError {
     /// The `Pulse` was dropped before it could `Pulse`
     Dropped,
+    /// The `Pulse` was cancelled with `Pulse::cancel`
+    Cancelled,
+    /// The `Pulse` was dropped while its thread was panicking
+    Panicked,
+    /// The `Pulse` failed with `Pulse::fail`
+    Failed(u32),
+    /// Waiting would never return, because the `Pulse` is claimed by
+    /// the waiting thread. See `Pulse::claim`.
+    Deadlock,
 }
 
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            WaitError::Dropped => write!(f, "the pulse was dropped without firing"),
+            WaitError::Cancelled => write!(f, "the pulse was cancelled"),
+            WaitError::Panicked => write!(f, "the pulse was dropped during a panic"),
+            WaitError::Failed(code) => write!(f, "the pulse failed with code {}", code),
+            WaitError::Deadlock => write!(f, "waiting on the pulse would deadlock"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WaitError {}
+
 /// Represents the possible errors from a wait timeout
 #[derive(Debug, PartialEq, Eq)]
 pub enum TimeoutError {
@@ -440,9 +974,28 @@ pub enum TimeoutError {
     Timeout,
 }
 
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TimeoutError::Error(ref err) => err.fmt(f),
+            TimeoutError::Timeout => write!(f, "timed out waiting for the pulse"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TimeoutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            TimeoutError::Error(ref err) => Some(err),
+            TimeoutError::Timeout => None,
+        }
+    }
+}
+
 struct ArmedSignal {
     pulse: Signal,
-    id: usize,
+    registration: Registration,
 }
 
 impl Deref for ArmedSignal {
@@ -455,7 +1008,7 @@ impl Deref for ArmedSignal {
 
 impl ArmedSignal {
     fn disarm(self) -> Signal {
-        self.remove_from_waitlist(self.id);
+        self.pulse.remove_from_waitlist(self.registration);
         self.pulse
     }
 }
@@ -483,34 +1036,63 @@ pub trait Signals {
 /// This is the hook into the async wait methods provided
 /// by `pulse`. It is required for the user to override
 /// the current system scheduler.
-pub trait Scheduler: std::fmt::Debug {
+pub trait Scheduler: fmt::Debug {
     /// Wait until the signal is made `ready` or `errored`
     fn wait(&self, signal: Signal) -> Result<(), WaitError>;
 
     /// Wait until the signal is made `ready` or `errored` or the
     /// timeout has been reached.
     fn wait_timeout_ms(&self, signal: Signal, timeout: u32) -> Result<(), TimeoutError>;
+
+    /// Get the `Unpark` for the task that is currently running on
+    /// this scheduler. By default this is the current OS thread.
+    #[cfg(feature = "std")]
+    fn unparker(&self) -> Arc<dyn Unpark> {
+        Arc::new(std::thread::current())
+    }
+
+    /// Get the `Unpark` for the task that is currently running on
+    /// this scheduler.
+    #[cfg(not(feature = "std"))]
+    fn unparker(&self) -> Arc<dyn Unpark>;
 }
 
 /// This is the `default` system scheduler that is used if no
 /// user provided scheduler is installed. It is very basic
 /// and will block the OS thread using `thread::park`
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct ThreadScheduler;
 
+#[cfg(feature = "std")]
 impl Scheduler for ThreadScheduler {
     fn wait(&self, signal: Signal) -> Result<(), WaitError> {
+        #[cfg(feature = "metrics")]
+        let mut parked = false;
+        #[cfg(feature = "metrics")]
+        metrics::waited();
+
         loop {
-            let id = signal.add_to_waitlist(Waiting::thread());
+            let registration = signal.add_to_waitlist(Waiting::thread());
             if signal.is_pending() {
-                thread::park();
+                #[cfg(feature = "metrics")]
+                {
+                    parked = true;
+                    metrics::parked();
+                }
+                sync::park();
             }
-            signal.remove_from_waitlist(id);
-
-            match signal.state() {
-                SignalState::Pending => (),
-                SignalState::Pulsed => return Ok(()),
-                SignalState::Dropped => return Err(WaitError::Dropped),
+            signal.remove_from_waitlist(registration);
+
+            if let Some(res) = signal.state().result() {
+                #[cfg(feature = "metrics")]
+                {
+                    if parked && res.is_ok() {
+                        let at = signal.inner().pulsed_at.load(atomic::Ordering::Relaxed);
+                        metrics::woke_after(at);
+                    }
+                }
+                return res;
             }
         }
     }
@@ -520,29 +1102,144 @@ impl Scheduler for ThreadScheduler {
         let ms = Duration::from_millis(ms as u64);
 
         loop {
-            let id = signal.add_to_waitlist(Waiting::thread());
+            let registration = signal.add_to_waitlist(Waiting::thread());
             if signal.is_pending() {
                 let elapsed = start.elapsed();
                 if elapsed > ms {
+                    signal.remove_from_waitlist(registration);
                     return Err(TimeoutError::Timeout);
                 }
-                thread::park_timeout(ms - elapsed);
+                sync::park_timeout(ms - elapsed);
+            }
+            signal.remove_from_waitlist(registration);
+
+            if let Some(res) = signal.state().result() {
+                return res.map_err(TimeoutError::Error);
+            }
+        }
+    }
+}
+
+/// Spinning backs off exponentially up to `1 << SPIN_LIMIT` spin
+/// hints per poll, after which it yields the thread instead.
+#[cfg(feature = "std")]
+const SPIN_LIMIT: u32 = 6;
+
+/// A scheduler that busy polls the `Signal` before falling back to
+/// parking the thread like `ThreadScheduler`. This avoids the cost
+/// of parking and unparking when the `Pulse` is expected to fire
+/// within a few microseconds, at the cost of burning cpu if it does not.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct SpinScheduler {
+    spins: u32,
+}
+
+#[cfg(feature = "std")]
+impl SpinScheduler {
+    /// Create a scheduler that will poll the `Signal` up to
+    /// `spins` times before parking the thread.
+    pub fn new(spins: u32) -> SpinScheduler {
+        SpinScheduler { spins }
+    }
+
+    /// Poll the signal with backoff, returns true if it is no longer pending
+    fn spin(&self, signal: &Signal, deadline: Option<Instant>) -> bool {
+        for step in 0..self.spins {
+            if !signal.is_pending() {
+                return true;
+            }
+            if deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+                return false;
+            }
+            if step < SPIN_LIMIT {
+                for _ in 0..(1 << step) {
+                    core::hint::spin_loop();
+                }
+            } else {
+                std::thread::yield_now();
             }
-            signal.remove_from_waitlist(id);
+        }
+        !signal.is_pending()
+    }
+}
 
-            match signal.state() {
-                SignalState::Pending => (),
-                SignalState::Pulsed => return Ok(()),
-                SignalState::Dropped => return Err(TimeoutError::Error(WaitError::Dropped)),
+#[cfg(feature = "std")]
+impl Scheduler for SpinScheduler {
+    fn wait(&self, signal: Signal) -> Result<(), WaitError> {
+        if self.spin(&signal, None) {
+            if let Some(res) = signal.state().result() {
+                return res;
+            }
+        }
+        ThreadScheduler.wait(signal)
+    }
+
+    fn wait_timeout_ms(&self, signal: Signal, ms: u32) -> Result<(), TimeoutError> {
+        let start = Instant::now();
+        if self.spin(&signal, Some(start + Duration::from_millis(ms as u64))) {
+            if let Some(res) = signal.state().result() {
+                return res.map_err(TimeoutError::Error);
             }
         }
+        let elapsed = start.elapsed().as_millis() as u32;
+        ThreadScheduler.wait_timeout_ms(signal, ms.saturating_sub(elapsed))
+    }
+}
+
+#[cfg(feature = "std")]
+type SchedulerFactory = Box<dyn Fn() -> Box<dyn Scheduler> + Send + Sync>;
+
+/// The factory used to create the scheduler of a thread that has not
+/// installed its own.
+#[cfg(feature = "std")]
+static DEFAULT_SCHEDULER: RwLock<Option<SchedulerFactory>> = RwLock::new(None);
+
+#[cfg(feature = "std")]
+fn default_scheduler() -> Box<dyn Scheduler> {
+    match *DEFAULT_SCHEDULER.read().unwrap() {
+        Some(ref factory) => factory(),
+        None => Box::new(GlobalScheduler),
     }
 }
 
-/// The TLS scheduler
-thread_local!(static SCHED: RefCell<Option<Box<Scheduler>>> = RefCell::new(Some(Box::new(ThreadScheduler))));
+/// Waits with the scheduler installed by `set_scheduler`, or parks the
+/// thread if there is none. This is looked up on every wait, so it also
+/// applies to threads that waited before `set_scheduler` was called.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct GlobalScheduler;
+
+#[cfg(feature = "std")]
+impl Scheduler for GlobalScheduler {
+    fn wait(&self, signal: Signal) -> Result<(), WaitError> {
+        match global_scheduler() {
+            Some(sched) => sched.wait(signal),
+            None => ThreadScheduler.wait(signal),
+        }
+    }
+
+    fn wait_timeout_ms(&self, signal: Signal, ms: u32) -> Result<(), TimeoutError> {
+        match global_scheduler() {
+            Some(sched) => sched.wait_timeout_ms(signal, ms),
+            None => ThreadScheduler.wait_timeout_ms(signal, ms),
+        }
+    }
+
+    fn unparker(&self) -> Arc<dyn Unpark> {
+        match global_scheduler() {
+            Some(sched) => sched.unparker(),
+            None => ThreadScheduler.unparker(),
+        }
+    }
+}
+
+// The TLS scheduler
+#[cfg(feature = "std")]
+thread_local!(static SCHED: RefCell<Option<Box<Scheduler>>> = RefCell::new(Some(default_scheduler())));
 
 // this is inline never to avoid the SCHED pointer being cached
+#[cfg(feature = "std")]
 #[inline(never)]
 fn take_scheduler() -> Option<Box<Scheduler>> {
     use std::mem;
@@ -551,11 +1248,64 @@ fn take_scheduler() -> Option<Box<Scheduler>> {
     sched
 }
 
+/// Run `f` with the TLS scheduler, it is taken out of the TLS for the
+/// duration so waiting from inside the scheduler is caught.
+#[cfg(feature = "std")]
+fn with_current_scheduler<F, R>(f: F) -> R
+    where F: FnOnce(&dyn Scheduler) -> R
+{
+    let s = take_scheduler().expect("no scheduler found");
+    let res = f(&*s);
+    swap_scheduler(s);
+    res
+}
+
+/// The scheduler installed with `set_scheduler`
+#[cfg(feature = "std")]
+static SCHEDULER: RwLock<Option<&'static (dyn Scheduler + Sync)>> = RwLock::new(None);
+#[cfg(not(feature = "std"))]
+static SCHEDULER: sync::Mutex<Option<&'static (dyn Scheduler + Sync)>> = sync::Mutex::new(None);
+
+#[cfg(feature = "std")]
+fn global_scheduler() -> Option<&'static (dyn Scheduler + Sync)> {
+    *SCHEDULER.read().unwrap()
+}
+
+#[cfg(not(feature = "std"))]
+fn global_scheduler() -> Option<&'static (dyn Scheduler + Sync)> {
+    *SCHEDULER.lock()
+}
+
+#[cfg(not(feature = "std"))]
+fn with_current_scheduler<F, R>(f: F) -> R
+    where F: FnOnce(&dyn Scheduler) -> R
+{
+    f(global_scheduler().expect("no scheduler found"))
+}
+
+/// Install the scheduler used by every `wait`. Without `std` there
+/// are no threads to give their own scheduler, so there is only the one.
+///
+/// With `std` this is used by every thread that has not installed its
+/// own scheduler with `swap_scheduler` or `with_scheduler`, and when no
+/// factory was set with `set_default_scheduler_factory`.
+pub fn set_scheduler(sched: &'static (dyn Scheduler + Sync)) {
+    #[cfg(feature = "std")]
+    {
+        *SCHEDULER.write().unwrap() = Some(sched);
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        *SCHEDULER.lock() = Some(sched);
+    }
+}
+
 /// Replace the current Scheduler with your own supplied scheduler.
 /// all `wait()` commands will be run through this scheduler now.
 ///
 /// This will return the current TLS scheduler, which may be useful
 /// to restore it later.
+#[cfg(feature = "std")]
 #[inline(never)]
 pub fn swap_scheduler(sched: Box<Scheduler>) -> Option<Box<Scheduler>> {
     use std::mem;
@@ -564,7 +1314,22 @@ pub fn swap_scheduler(sched: Box<Scheduler>) -> Option<Box<Scheduler>> {
     sched
 }
 
+/// Install a process wide factory for schedulers. Every thread
+/// that has not yet used its TLS scheduler will call the factory
+/// to create one the first time it waits. This includes threads
+/// spawned by other libraries.
+///
+/// A scheduler installed with `swap_scheduler` or `with_scheduler`
+/// still takes precedence for the thread that installed it.
+#[cfg(feature = "std")]
+pub fn set_default_scheduler_factory<F>(factory: F)
+    where F: Fn() -> Box<dyn Scheduler> + Send + Sync + 'static
+{
+    *DEFAULT_SCHEDULER.write().unwrap() = Some(Box::new(factory));
+}
+
 /// Call the suppled closure using the supplied schedulee
+#[cfg(feature = "std")]
 pub fn with_scheduler<F>(f: F, sched: Box<Scheduler>) -> Option<Box<Scheduler>>
     where F: FnOnce()
 {
@@ -572,3 +1337,45 @@ pub fn with_scheduler<F>(f: F, sched: Box<Scheduler>) -> Option<Box<Scheduler>>
     f();
     old.and_then(|o| swap_scheduler(o))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flags_are_the_top_bits() {
+        assert_eq!(PULSED.leading_zeros(), 0);
+        assert_eq!(TX_DROP.leading_zeros(), 1);
+        assert_eq!(CLOSED.leading_zeros(), 2);
+        assert_eq!(REF_COUNT & (TX_FLAGS | CLOSED), 0);
+        assert_eq!(REF_COUNT.leading_zeros(), 3);
+    }
+
+    #[test]
+    fn max_ref_count_per_width() {
+        assert_eq!(MAX_REF_COUNT as u64, max_ref_count(usize::BITS));
+        // a 32 bit target can still have about 2^28 signals
+        assert_eq!(max_ref_count(32), (1 << 28) - 1);
+        assert_eq!(max_ref_count(64), (1 << 60) - 1);
+    }
+
+    #[test]
+    fn clone_at_max_ref_count() {
+        let (s, p) = Signal::new();
+
+        // stand in for MAX_REF_COUNT - 2 leaked signals
+        let leaked = MAX_REF_COUNT - 2;
+        s.inner().state.fetch_add(leaked, Ordering::Relaxed);
+        let c = s.clone();
+        assert_eq!(s.inner().state.load(Ordering::Relaxed), MAX_REF_COUNT + 1);
+        assert!(c.is_pending());
+
+        p.pulse();
+        assert_eq!(c.state(), SignalState::Pulsed);
+        assert_eq!(s.inner().state.load(Ordering::Relaxed) & REF_COUNT, MAX_REF_COUNT);
+
+        s.inner().state.fetch_sub(leaked, Ordering::Relaxed);
+        drop(c);
+        drop(s);
+    }
+}
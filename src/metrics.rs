@@ -0,0 +1,157 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Process wide counters of what signals are doing, enabled by the
+//! `metrics` feature. The counters are updated with relaxed atomics,
+//! so a `snapshot` taken while other threads are busy is not exact.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The number of buckets in a `Histogram`
+pub const BUCKETS: usize = 64;
+
+struct Metrics {
+    pulsed: AtomicU64,
+    dropped: AtomicU64,
+    waits: AtomicU64,
+    parks: AtomicU64,
+    wakes: AtomicU64,
+    latency: [AtomicU64; BUCKETS],
+}
+
+static METRICS: Metrics = Metrics {
+    pulsed: AtomicU64::new(0),
+    dropped: AtomicU64::new(0),
+    waits: AtomicU64::new(0),
+    parks: AtomicU64::new(0),
+    wakes: AtomicU64::new(0),
+    latency: [const { AtomicU64::new(0) }; BUCKETS],
+};
+
+fn inc(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn pulsed() {
+    inc(&METRICS.pulsed)
+}
+
+pub(crate) fn dropped() {
+    inc(&METRICS.dropped)
+}
+
+pub(crate) fn waited() {
+    inc(&METRICS.waits)
+}
+
+pub(crate) fn parked() {
+    inc(&METRICS.parks)
+}
+
+pub(crate) fn woke() {
+    inc(&METRICS.wakes)
+}
+
+/// Nanoseconds since the first call, never zero so that zero can be
+/// used for a time that was never recorded.
+pub(crate) fn now() -> u64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64 + 1
+}
+
+/// Record the latency of a waiter that woke up after a pulse at `at`
+pub(crate) fn woke_after(at: u64) {
+    if at == 0 {
+        return;
+    }
+    let latency = now().saturating_sub(at);
+    let bucket = (u64::BITS - latency.leading_zeros()) as usize;
+    inc(&METRICS.latency[bucket.min(BUCKETS - 1)]);
+}
+
+/// A histogram with power of two buckets. Bucket `0` counts values of
+/// zero, and bucket `i` counts values in `[2^(i-1), 2^i)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS],
+}
+
+impl Histogram {
+    /// The count of every bucket
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// The total number of values recorded
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// An upper bound of the `q`th quantile, where `q` is between 0
+    /// and 1. This is `None` if nothing was recorded.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((count as f64 * q).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some(Duration::from_nanos(1u64.checked_shl(i as u32).unwrap_or(u64::MAX)));
+            }
+        }
+        None
+    }
+}
+
+/// The counters at the time `snapshot` was called
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Pulses that fired
+    pub pulsed: u64,
+    /// Pulses that were dropped without firing, this includes
+    /// `Pulse::fail` and `Pulse::cancel`
+    pub dropped: u64,
+    /// Calls to `ThreadScheduler::wait`
+    pub waits: u64,
+    /// Times `ThreadScheduler` parked a thread
+    pub parks: u64,
+    /// Waiters that were woken
+    pub wakes: u64,
+    /// Nanoseconds from a pulse firing to a parked thread that was
+    /// waiting on it running again
+    pub latency: Histogram,
+}
+
+/// Read out every counter
+pub fn snapshot() -> Snapshot {
+    let mut buckets = [0; BUCKETS];
+    for (b, n) in buckets.iter_mut().zip(METRICS.latency.iter()) {
+        *b = n.load(Ordering::Relaxed);
+    }
+
+    Snapshot {
+        pulsed: METRICS.pulsed.load(Ordering::Relaxed),
+        dropped: METRICS.dropped.load(Ordering::Relaxed),
+        waits: METRICS.waits.load(Ordering::Relaxed),
+        parks: METRICS.parks.load(Ordering::Relaxed),
+        wakes: METRICS.wakes.load(Ordering::Relaxed),
+        latency: Histogram { buckets },
+    }
+}
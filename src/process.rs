@@ -0,0 +1,98 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! Signals that are pulsed when a child process exits.
+//!
+//! On Linux the child is watched with a pidfd by the `reactor` thread.
+//! Where pidfds are not available a thread is started for every child
+//! that blocks in `waitid` until it exits.
+//!
+//! The child is never reaped, so its exit status can still be read
+//! with `Child::wait` or `Child::try_wait` once the signal is pulsed.
+
+use std::io;
+use std::mem;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::{FromRawFd, OwnedFd};
+use std::process::Child;
+use std::thread;
+
+use libc;
+
+use Signal;
+
+/// Create a `Signal` that will be pulsed once `child` has exited. If it
+/// has already been waited on the signal is already pulsed.
+///
+/// The signal is dropped if the child can no longer be waited on, for
+/// example because something other than `child` reaped it.
+pub fn exited(child: &mut Child) -> io::Result<Signal> {
+    if child.try_wait()?.is_some() {
+        return Ok(Signal::pulsed());
+    }
+
+    // The child has not been reaped, so its pid cannot be reused by
+    // another process until it is.
+    let pid = child.id() as libc::pid_t;
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(fd) = pidfd(pid)? {
+            return Ok(::reactor::readable_owned(fd));
+        }
+    }
+
+    wait_thread(pid)
+}
+
+/// Open a pidfd, or `None` if the kernel does not support them
+#[cfg(target_os = "linux")]
+fn pidfd(pid: libc::pid_t) -> io::Result<Option<OwnedFd>> {
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if fd < 0 {
+        let err = io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENOSYS) | Some(libc::EPERM) => Ok(None),
+            _ => Err(err),
+        };
+    }
+    Ok(Some(unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) }))
+}
+
+fn wait_thread(pid: libc::pid_t) -> io::Result<Signal> {
+    let (signal, pulse) = Signal::new();
+    thread::Builder::new()
+        .name("pulse-process".into())
+        .spawn(move || {
+            loop {
+                let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+                let res = unsafe {
+                    libc::waitid(libc::P_PID,
+                                 pid as libc::id_t,
+                                 &mut info,
+                                 libc::WEXITED | libc::WNOWAIT)
+                };
+                if res == 0 {
+                    pulse.pulse();
+                    return;
+                }
+                if io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                    // ECHILD, dropping the pulse tells the signal
+                    return;
+                }
+            }
+        })?;
+    Ok(signal)
+}
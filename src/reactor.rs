@@ -0,0 +1,183 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! Signals that are pulsed when a file descriptor becomes ready.
+//!
+//! All file descriptors are watched by a single background thread
+//! using `poll`, which is started the first time one is registered.
+//! Like the `timer`, a registration is forgotten once nothing is
+//! listening to it, this is checked whenever a new file descriptor is
+//! registered or one becomes ready.
+//!
+//! The reactor watches a duplicate of the descriptor it was given, so
+//! closing the original does not affect it. The duplicate is closed
+//! once the signal is pulsed or forgotten, until then it keeps the
+//! underlying file or socket open.
+
+use std::cmp;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsFd, AsRawFd, OwnedFd};
+use std::sync::Mutex;
+
+use libc;
+
+use background::{fire, Background};
+use fd::EventFd;
+use {Pulse, Signal, Unpark};
+
+struct Entry {
+    fd: OwnedFd,
+    events: libc::c_short,
+    pulse: Pulse,
+}
+
+struct Reactor {
+    entries: Mutex<Vec<Entry>>,
+    // Written when an entry is added, so that `poll` starts over
+    // with the new entry included.
+    wake: EventFd,
+}
+
+impl Reactor {
+    fn add(&self, fd: OwnedFd, events: libc::c_short) -> Signal {
+        let (signal, pulse) = Signal::new();
+        self.entries.lock().unwrap().push(Entry {
+            fd,
+            events,
+            pulse,
+        });
+        self.wake.unpark();
+        signal
+    }
+
+    /// Fail the registrations that do not fit under `RLIMIT_NOFILE`
+    /// with `code`, or all of them if they already fit. `poll` fails
+    /// with `EINVAL` once it is given more descriptors than the limit,
+    /// and retrying would never succeed.
+    #[cold]
+    fn fail_newest(&self, code: u32) {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        let keep = if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } == 0 {
+            // one descriptor is taken by `wake`
+            (cmp::min(limit.rlim_cur, usize::MAX as libc::rlim_t) as usize).saturating_sub(1)
+        } else {
+            0
+        };
+
+        let failed = {
+            let mut entries = self.entries.lock().unwrap();
+            let keep = if entries.len() > keep { keep } else { 0 };
+            entries.split_off(keep)
+        };
+        for entry in failed {
+            entry.pulse.fail(code);
+        }
+    }
+
+    fn run(&self) {
+        let mut fds = Vec::new();
+        let mut ready = Vec::new();
+        loop {
+            fds.clear();
+            fds.push(libc::pollfd {
+                fd: self.wake.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            });
+            // Abandoned entries are dropped after the lock is released
+            let swept: Vec<Entry> = {
+                let mut entries = self.entries.lock().unwrap();
+                let (live, abandoned) = mem::take(&mut *entries)
+                    .into_iter()
+                    .partition(|e| !e.pulse.is_abandoned());
+                *entries = live;
+                fds.extend(entries.iter().map(|e| {
+                    libc::pollfd {
+                        fd: e.fd.as_raw_fd(),
+                        events: e.events,
+                        revents: 0,
+                    }
+                }));
+                abandoned
+            };
+            drop(swept);
+
+            let n = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+            if n < 0 {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::EINTR) | Some(libc::ENOMEM) => (),
+                    code => self.fail_newest(code.unwrap_or(0) as u32),
+                }
+                continue;
+            }
+            if fds[0].revents != 0 {
+                self.wake.reset();
+            }
+
+            {
+                // Entries are only ever appended while we were polling,
+                // so the first `fds.len() - 1` are the ones polled. They
+                // are removed from the back so the indexes stay valid.
+                let mut entries = self.entries.lock().unwrap();
+                for (i, fd) in fds.iter().enumerate().skip(1).rev() {
+                    if fd.revents != 0 {
+                        ready.push(entries.swap_remove(i - 1));
+                    }
+                }
+            }
+
+            fire(ready.drain(..).map(|e| e.pulse));
+        }
+    }
+}
+
+fn reactor() -> &'static Reactor {
+    static REACTOR: Background<Reactor> = Background::new();
+
+    REACTOR.get("pulse-reactor",
+                || {
+                    Reactor {
+                        entries: Mutex::new(Vec::new()),
+                        wake: EventFd::new().expect("failed to create the pulse reactor eventfd"),
+                    }
+                },
+                Reactor::run)
+}
+
+/// Create a `Signal` that will be pulsed once `fd` is readable, or
+/// has hung up or has an error pending. This fails if `fd` cannot be
+/// duplicated. If the reactor cannot poll it, for example because
+/// `RLIMIT_NOFILE` was lowered below the number of registrations, the
+/// signal fails with the `errno` of `poll`.
+pub fn readable<T: AsFd + ?Sized>(fd: &T) -> io::Result<Signal> {
+    Ok(readable_owned(fd.as_fd().try_clone_to_owned()?))
+}
+
+/// Like `readable`, but the reactor watches `fd` itself rather than a
+/// duplicate of it.
+pub(crate) fn readable_owned(fd: OwnedFd) -> Signal {
+    reactor().add(fd, libc::POLLIN)
+}
+
+/// Create a `Signal` that will be pulsed once `fd` is writable, or
+/// has hung up or has an error pending. This fails like `readable`.
+pub fn writable<T: AsFd + ?Sized>(fd: &T) -> io::Result<Signal> {
+    Ok(reactor().add(fd.as_fd().try_clone_to_owned()?, libc::POLLOUT))
+}
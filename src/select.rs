@@ -13,16 +13,21 @@
 //   limitations under the License.
 
 
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
-use {Signal, ArmedSignal, Pulse, Waiting, Barrier, Signals};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use sync::Mutex;
+#[cfg(feature = "tracing")]
+use trace;
+use {Signal, SignalId, ArmedSignal, Pulse, Waiting, Barrier, Signals};
 
 pub struct Inner {
     pub ready: Vec<usize>,
     pub trigger: Option<Pulse>,
 }
 
-pub struct Handle(pub Arc<Mutex<Inner>>);
+/// The `Select` to wake and the key of the entry that fired
+pub struct Handle(pub Arc<Mutex<Inner>>, pub usize);
 
 /// A `Select` listens to 1 or more signals. It will wait until
 /// any signal becomes available before Pulsing. `Select` will then
@@ -31,7 +36,12 @@ pub struct Handle(pub Arc<Mutex<Inner>>);
 /// pending.
 pub struct Select {
     inner: Arc<Mutex<Inner>>,
-    signals: HashMap<usize, ArmedSignal>,
+    // Entries are keyed per `add` rather than by `SignalId`, so the
+    // same signal can be added more than once.
+    signals: BTreeMap<usize, ArmedSignal>,
+    // The keys of every entry of a signal, oldest first
+    ids: BTreeMap<SignalId, Vec<usize>>,
+    next_key: usize,
 }
 
 impl Select {
@@ -42,25 +52,47 @@ impl Select {
                 ready: Vec::new(),
                 trigger: None,
             })),
-            signals: HashMap::new(),
+            signals: BTreeMap::new(),
+            ids: BTreeMap::new(),
+            next_key: 0,
         }
     }
 
     /// Add a signal to the `Select`, a unique id that is associated
     /// With the signal is returned. This can be used to remove the
     /// signal from the `Select` or to lookup the `Pulse` when it fires.
-    pub fn add(&mut self, pulse: Signal) -> usize {
+    ///
+    /// A signal that is added more than once will be returned once
+    /// for every time it was added.
+    pub fn add(&mut self, pulse: Signal) -> SignalId {
         let id = pulse.id();
-        let p = pulse.arm(Waiting::select(Handle(self.inner.clone())));
-        self.signals.insert(id, p);
+        self.add_entry(pulse);
         id
     }
 
-    /// Remove a `Signal1 from the `Select` using it's unique id.
-    pub fn remove(&mut self, id: usize) -> Option<Signal> {
-        self.signals
-            .remove(&id)
-            .map(|x| x.disarm())
+    fn add_entry(&mut self, pulse: Signal) -> usize {
+        let key = self.next_key;
+        self.next_key += 1;
+        self.ids.entry(pulse.id()).or_default().push(key);
+        let p = pulse.arm(Waiting::select(Handle(self.inner.clone(), key)));
+        self.signals.insert(key, p);
+        key
+    }
+
+
+    /// Remove a `Signal1 from the `Select` using it's unique id. If
+    /// the signal was added more than once only one of them is removed.
+    pub fn remove(&mut self, id: SignalId) -> Option<Signal> {
+        let key = *self.ids.get(&id)?.first()?;
+
+        take(&mut self.signals, &mut self.ids, key).map(|p| {
+            // A waker that already took the entry off the waitlist can
+            // still push its key after this, keys are never reused so
+            // the readers skip keys that are no longer in `signals`.
+            let signal = p.disarm();
+            self.inner.lock().ready.retain(|&x| x != key);
+            signal
+        })
     }
 
     /// Convert all the signals present in the `Select` into a `Barrier`
@@ -77,11 +109,16 @@ impl Select {
     /// this will return a `Some(Signal)` if there is a pending `Signal`
     /// in the select. Otherwise it will return `None`
     pub fn try_next(&mut self) -> Option<Signal> {
-        let mut guard = self.inner.lock().unwrap();
-        if let Some(x) = guard.ready.pop() {
-            return Some(self.signals.remove(&x).map(|x| x.disarm()).unwrap());
+        self.try_next_entry().map(|(_, x)| x)
+    }
+
+    fn try_next_entry(&mut self) -> Option<(usize, Signal)> {
+        loop {
+            let key = self.inner.lock().ready.pop()?;
+            if let Some(p) = take(&mut self.signals, &mut self.ids, key) {
+                return Some((key, ready(p)));
+            }
         }
-        None
     }
 
     /// Get the number of Signals being watched
@@ -90,20 +127,18 @@ impl Select {
     }
 }
 
-impl Iterator for Select {
-    type Item = Signal;
-
-    fn next(&mut self) -> Option<Signal> {
+impl Select {
+    fn next_entry(&mut self) -> Option<(usize, Signal)> {
         loop {
             if self.signals.len() == 0 {
                 return None;
             }
 
             let pulse = {
-                let mut guard = self.inner.lock().unwrap();
+                let mut guard = self.inner.lock();
                 while let Some(x) = guard.ready.pop() {
-                    if let Some(x) = self.signals.remove(&x) {
-                        return Some(x.disarm());
+                    if let Some(p) = take(&mut self.signals, &mut self.ids, x) {
+                        return Some((x, ready(p)));
                     }
                 }
                 let (pulse, t) = Signal::new();
@@ -115,10 +150,40 @@ impl Iterator for Select {
     }
 }
 
+/// Remove the entry for `key` if it is still in the `Select`, this
+/// does not borrow the `Select` so its lock can be held meanwhile.
+fn take(signals: &mut BTreeMap<usize, ArmedSignal>,
+        ids: &mut BTreeMap<SignalId, Vec<usize>>,
+        key: usize)
+        -> Option<ArmedSignal> {
+    let p = signals.remove(&key)?;
+    let keys = ids.get_mut(&p.id()).unwrap();
+    keys.retain(|&k| k != key);
+    if keys.is_empty() {
+        ids.remove(&p.id());
+    }
+    Some(p)
+}
+
+fn ready(signal: ArmedSignal) -> Signal {
+    let signal = signal.disarm();
+    #[cfg(feature = "tracing")]
+    trace::ready(&signal);
+    signal
+}
+
+impl Iterator for Select {
+    type Item = Signal;
+
+    fn next(&mut self) -> Option<Signal> {
+        self.next_entry().map(|(_, x)| x)
+    }
+}
+
 impl Signals for Select {
     fn signal(&self) -> Signal {
         let (pulse, t) = Signal::new();
-        let mut guard = self.inner.lock().unwrap();
+        let mut guard = self.inner.lock();
         if guard.ready.len() == 0 {
             guard.trigger = Some(t);
         } else {
@@ -133,7 +198,7 @@ impl Signals for Select {
 /// will return an supplied object.
 pub struct SelectMap<T> {
     select: Select,
-    items: HashMap<usize, T>,
+    items: BTreeMap<usize, T>,
 }
 
 impl<T> SelectMap<T> {
@@ -141,24 +206,21 @@ impl<T> SelectMap<T> {
     pub fn new() -> SelectMap<T> {
         SelectMap {
             select: Select::new(),
-            items: HashMap::new(),
+            items: BTreeMap::new(),
         }
     }
 
     /// Add a `Signal` and an associated value into the `SelectMap`
     pub fn add(&mut self, signal: Signal, value: T) {
-        let id = self.select.add(signal);
-        self.items.insert(id, value);
+        let key = self.select.add_entry(signal);
+        self.items.insert(key, value);
     }
 
     /// This is a non-blocking attempt to get a `Signal` from a `SelectMap`
     /// this will return a `Some((Signal, T))` if there is a pending `Signal`
     /// in the select. Otherwise it will return `None`
     pub fn try_next(&mut self) -> Option<(Signal, T)> {
-        self.select.try_next().map(|x| {
-            let id = x.id();
-            (x, self.items.remove(&id).unwrap())
-        })
+        self.select.try_next_entry().map(|(key, x)| (x, self.items.remove(&key).unwrap()))
     }
 
     /// Get the number of items in the `SelectMap`
@@ -171,10 +233,7 @@ impl<T> Iterator for SelectMap<T> {
     type Item = (Signal, T);
 
     fn next(&mut self) -> Option<(Signal, T)> {
-        self.select.next().map(|x| {
-            let id = x.id();
-            (x, self.items.remove(&id).unwrap())
-        })
+        self.select.next_entry().map(|(key, x)| (x, self.items.remove(&key).unwrap()))
     }
 }
 
@@ -0,0 +1,175 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! The atomics, locks and thread parking used by the `Signal` state machine.
+//!
+//! When built with `RUSTFLAGS="--cfg loom"` these are swapped for the
+//! model checked versions from `loom`, see `tests/loom.rs`. Without the
+//! `std` feature there is no thread parking and `Mutex` is a spinlock.
+
+#[cfg(not(loom))]
+pub use core::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+#[cfg(all(not(loom), feature = "std"))]
+pub use std::thread::{current, park, park_timeout, Thread};
+#[cfg(not(loom))]
+pub use core::hint::spin_loop;
+
+#[cfg(loom)]
+pub use loom::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+#[cfg(loom)]
+pub use loom::thread::{current, park, Thread};
+// A spinning thread cannot make progress until another one runs, so
+// tell loom to schedule someone else rather than exploring the spins.
+#[cfg(loom)]
+pub use loom::thread::yield_now as spin_loop;
+
+/// loom has no notion of time, so a timed park is modelled as a park
+/// that can only be ended by an `unpark`.
+#[cfg(loom)]
+pub fn park_timeout(_: ::std::time::Duration) {
+    park()
+}
+
+#[cfg(feature = "std")]
+pub use std::process::abort;
+#[cfg(feature = "std")]
+pub use std::thread::panicking;
+
+/// Without `std` there is no unwinding to detect
+#[cfg(not(feature = "std"))]
+pub fn panicking() -> bool {
+    false
+}
+
+/// Without `std` there is no way to abort directly, but a panic while
+/// unwinding from another panic always aborts.
+#[cfg(not(feature = "std"))]
+pub fn abort() -> ! {
+    struct Abort;
+    impl Drop for Abort {
+        fn drop(&mut self) {
+            panic!("abort");
+        }
+    }
+
+    let _abort = Abort;
+    panic!("abort");
+}
+
+#[cfg(all(not(loom), feature = "std"))]
+pub use self::std_mutex::Mutex;
+#[cfg(loom)]
+pub use self::loom_mutex::Mutex;
+#[cfg(not(feature = "std"))]
+pub use self::spin_mutex::Mutex;
+
+#[cfg(all(not(loom), feature = "std"))]
+mod std_mutex {
+    use std::sync;
+
+    pub use std::sync::MutexGuard;
+
+    /// A `std::sync::Mutex` that panics if it was poisoned
+    pub struct Mutex<T>(sync::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub const fn new(value: T) -> Mutex<T> {
+            Mutex(sync::Mutex::new(value))
+        }
+
+        pub fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap()
+        }
+    }
+}
+
+/// loom's `Mutex` cannot be built in a `const`, so unlike the others
+/// this one cannot be used in a `static`.
+#[cfg(loom)]
+mod loom_mutex {
+    use loom::sync;
+
+    pub use loom::sync::MutexGuard;
+
+    pub struct Mutex<T>(sync::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub fn new(value: T) -> Mutex<T> {
+            Mutex(sync::Mutex::new(value))
+        }
+
+        pub fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap()
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod spin_mutex {
+    use core::cell::UnsafeCell;
+    use core::ops::{Deref, DerefMut};
+
+    use super::{spin_loop, AtomicBool, Ordering};
+
+    /// A spinlock, only suitable for locks that are held very briefly
+    pub struct Mutex<T> {
+        locked: AtomicBool,
+        value: UnsafeCell<T>,
+    }
+
+    unsafe impl<T: Send> Send for Mutex<T> {}
+    unsafe impl<T: Send> Sync for Mutex<T> {}
+
+    impl<T> Mutex<T> {
+        pub const fn new(value: T) -> Mutex<T> {
+            Mutex {
+                locked: AtomicBool::new(false),
+                value: UnsafeCell::new(value),
+            }
+        }
+
+        pub fn lock(&self) -> MutexGuard<'_, T> {
+            while self.locked
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err() {
+                spin_loop();
+            }
+            MutexGuard { lock: self }
+        }
+    }
+
+    pub struct MutexGuard<'a, T: 'a> {
+        lock: &'a Mutex<T>,
+    }
+
+    impl<'a, T> Deref for MutexGuard<'a, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            unsafe { &*self.lock.value.get() }
+        }
+    }
+
+    impl<'a, T> DerefMut for MutexGuard<'a, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            unsafe { &mut *self.lock.value.get() }
+        }
+    }
+
+    impl<'a, T> Drop for MutexGuard<'a, T> {
+        fn drop(&mut self) {
+            self.lock.locked.store(false, Ordering::Release);
+        }
+    }
+}
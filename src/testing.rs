@@ -0,0 +1,129 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Tools for testing code that is built on `pulse`.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use {Pulse, Scheduler, Select, Signal, Signals, ThreadScheduler, TimeoutError, WaitError};
+
+#[derive(Debug)]
+struct Clock {
+    now: Duration,
+    timers: Vec<(Duration, Pulse)>,
+}
+
+/// A `Scheduler` with a virtual clock that only moves when `advance`
+/// is called. Timeouts passed to `wait_timeout_ms` are measured
+/// against the virtual clock, so a test can make them fire without
+/// ever sleeping.
+///
+/// Clones of a `VirtualScheduler` share the same clock, so one clone
+/// can be installed in each thread under test while the test itself
+/// holds on to another to drive the clock.
+#[derive(Clone, Debug)]
+pub struct VirtualScheduler {
+    clock: Arc<Mutex<Clock>>,
+}
+
+impl Default for VirtualScheduler {
+    fn default() -> VirtualScheduler {
+        VirtualScheduler::new()
+    }
+}
+
+impl VirtualScheduler {
+    /// Create a scheduler with its clock set to zero
+    pub fn new() -> VirtualScheduler {
+        VirtualScheduler {
+            clock: Arc::new(Mutex::new(Clock {
+                now: Duration::from_secs(0),
+                timers: Vec::new(),
+            })),
+        }
+    }
+
+    /// The amount of virtual time that has passed
+    pub fn now(&self) -> Duration {
+        self.clock.lock().unwrap().now
+    }
+
+    /// Move the virtual clock forward, this pulses every timeout
+    /// that expires along the way in the order of their deadlines.
+    pub fn advance(&self, by: Duration) {
+        let mut expired = {
+            let mut guard = self.clock.lock().unwrap();
+            guard.now += by;
+            let now = guard.now;
+            let (expired, pending) = guard.timers.drain(..).partition(|t| t.0 <= now);
+            guard.timers = pending;
+            expired
+        };
+
+        expired.sort_by_key(|t: &(Duration, Pulse)| t.0);
+        for (_, pulse) in expired {
+            pulse.pulse();
+        }
+    }
+
+    /// Create a `Signal` that is pulsed once the virtual clock has
+    /// moved forward by `after`.
+    pub fn timeout(&self, after: Duration) -> Signal {
+        if after == Duration::from_secs(0) {
+            return Signal::pulsed();
+        }
+
+        let (signal, pulse) = Signal::new();
+        let mut guard = self.clock.lock().unwrap();
+        let deadline = guard.now + after;
+        guard.timers.push((deadline, pulse));
+        signal
+    }
+
+    /// The number of timeouts that have not yet expired and are still
+    /// being waited on. This is useful to find out if a thread under
+    /// test has reached a `wait_timeout_ms` before advancing the clock.
+    pub fn sleepers(&self) -> usize {
+        // The abandoned timeouts are dropped once the lock is released
+        let (count, _abandoned) = {
+            let mut guard = self.clock.lock().unwrap();
+            let (live, abandoned): (Vec<_>, Vec<_>) =
+                guard.timers.drain(..).partition(|t| !t.1.is_abandoned());
+            guard.timers = live;
+            (guard.timers.len(), abandoned)
+        };
+        count
+    }
+}
+
+impl Scheduler for VirtualScheduler {
+    fn wait(&self, signal: Signal) -> Result<(), WaitError> {
+        ThreadScheduler.wait(signal)
+    }
+
+    fn wait_timeout_ms(&self, signal: Signal, ms: u32) -> Result<(), TimeoutError> {
+        if signal.is_pending() {
+            let mut select = Select::new();
+            select.add(signal.clone());
+            select.add(self.timeout(Duration::from_millis(ms as u64)));
+            let _ = ThreadScheduler.wait(select.signal());
+        }
+
+        match signal.state().result() {
+            None => Err(TimeoutError::Timeout),
+            Some(res) => res.map_err(TimeoutError::Error),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! Threads that come with a `Signal` for when they finish.
+//!
+//! The signal is pulsed once the thread's closure returns, and is
+//! `Panicked` if it panics instead. This lets a `Select` or
+//! `SelectMap` join whichever thread finishes first.
+
+use std::io;
+use std::thread::{Builder, JoinHandle};
+
+use {Pulse, Signal};
+
+/// Spawn a thread running `f`, like `std::thread::spawn`. The signal is
+/// pulsed when `f` returns, the thread's result can then be taken with
+/// `JoinHandle::join` without blocking for long.
+pub fn spawn<F, T>(f: F) -> (Signal, JoinHandle<T>)
+    where F: FnOnce() -> T + Send + 'static,
+          T: Send + 'static
+{
+    spawn_with(Builder::new(), f).expect("failed to spawn thread")
+}
+
+/// Spawn a thread running `f` with the name and stack size set on
+/// `builder`. This fails if the thread could not be created.
+pub fn spawn_with<F, T>(builder: Builder, f: F) -> io::Result<(Signal, JoinHandle<T>)>
+    where F: FnOnce() -> T + Send + 'static,
+          T: Send + 'static
+{
+    let (signal, pulse) = Signal::new();
+    let join = builder.spawn(move || run(pulse, f))?;
+    Ok((signal, join))
+}
+
+fn run<F, T>(pulse: Pulse, f: F) -> T
+    where F: FnOnce() -> T
+{
+    // If `f` panics the pulse is dropped while unwinding, which leaves
+    // the signal `Panicked`.
+    let res = f();
+    pulse.pulse();
+    res
+}
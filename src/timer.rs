@@ -0,0 +1,251 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Signals that are pulsed by the passage of time.
+//!
+//! All timers share a single background thread which is started the
+//! first time a timer is created. A timer is cancelled once nothing is
+//! listening to it, see `Pulse::is_abandoned`, so timers that lose a
+//! race inside of a `Select` do not accumulate.
+//!
+//! A `Ticker` uses the same thread to produce a heartbeat that can sit
+//! alongside other signals in a `Select`.
+
+use std::cmp;
+use std::collections::BinaryHeap;
+use std::mem;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use background::{fire, Background};
+use {Pulse, Signal, Signals};
+
+/// Abandoned timers are only swept when the heap grows past this
+/// many entries, or twice the number of entries that survived the
+/// last sweep.
+const MIN_SWEEP: usize = 64;
+
+struct Entry {
+    deadline: Instant,
+    seq: u64,
+    pulse: Pulse,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    // `BinaryHeap` is a max-heap, so the order is reversed to
+    // keep the earliest deadline on top.
+    fn cmp(&self, other: &Entry) -> cmp::Ordering {
+        (other.deadline, other.seq).cmp(&(self.deadline, self.seq))
+    }
+}
+
+struct Queue {
+    heap: BinaryHeap<Entry>,
+    seq: u64,
+    sweep_at: usize,
+}
+
+struct Timer {
+    queue: Mutex<Queue>,
+    cond: Condvar,
+}
+
+impl Timer {
+    fn add(&self, deadline: Instant, pulse: Pulse) {
+        let mut guard = self.queue.lock().unwrap();
+        // The swept pulses are dropped once the lock is released, like
+        // the ones that are fired.
+        let mut swept = Vec::new();
+        if guard.heap.len() >= guard.sweep_at {
+            let (live, abandoned) = mem::take(&mut guard.heap)
+                .into_vec()
+                .into_iter()
+                .partition(|e| !e.pulse.is_abandoned());
+            swept = abandoned;
+            guard.heap = BinaryHeap::from(live);
+            guard.sweep_at = cmp::max(MIN_SWEEP, guard.heap.len() * 2);
+        }
+
+        let seq = guard.seq;
+        guard.seq += 1;
+        let wake = guard.heap.peek().map(|e| deadline < e.deadline).unwrap_or(true);
+        guard.heap.push(Entry {
+            deadline,
+            seq,
+            pulse,
+        });
+
+        // only the timer thread cares if the next deadline moved
+        if wake {
+            self.cond.notify_one();
+        }
+        drop(guard);
+        drop(swept);
+    }
+
+    fn run(&self) {
+        let mut expired = Vec::new();
+        let mut guard = self.queue.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let mut next = None;
+            while let Some(entry) = guard.heap.peek() {
+                if entry.pulse.is_abandoned() || entry.deadline <= now {
+                    expired.push(guard.heap.pop().unwrap());
+                } else {
+                    next = Some(entry.deadline - now);
+                    break;
+                }
+            }
+
+            if !expired.is_empty() {
+                drop(guard);
+                fire(expired.drain(..).map(|e| e.pulse));
+                guard = self.queue.lock().unwrap();
+                continue;
+            }
+
+            guard = match next {
+                Some(timeout) => self.cond.wait_timeout(guard, timeout).unwrap().0,
+                None => self.cond.wait(guard).unwrap(),
+            };
+        }
+    }
+}
+
+fn timer() -> &'static Timer {
+    static TIMER: Background<Timer> = Background::new();
+
+    TIMER.get("pulse-timer",
+              || {
+                  Timer {
+                      queue: Mutex::new(Queue {
+                          heap: BinaryHeap::new(),
+                          seq: 0,
+                          sweep_at: MIN_SWEEP,
+                      }),
+                      cond: Condvar::new(),
+                  }
+              },
+              Timer::run)
+}
+
+/// Create a `Signal` that will be pulsed once `duration` has elapsed.
+pub fn after(duration: Duration) -> Signal {
+    at(Instant::now() + duration)
+}
+
+/// Create a `Signal` that will be pulsed once `deadline` has been
+/// reached. A deadline in the past returns a `Signal` that is
+/// already pulsed.
+pub fn at(deadline: Instant) -> Signal {
+    if deadline <= Instant::now() {
+        return Signal::pulsed();
+    }
+
+    let (signal, pulse) = Signal::new();
+    timer().add(deadline, pulse);
+    signal
+}
+
+/// What a `Ticker` should do when a tick was not consumed before the
+/// following one was due.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissedTick {
+    /// Fire every missed tick back to back until the `Ticker` has
+    /// caught up with its original schedule.
+    Burst,
+    /// Fire once for all of the missed ticks, then continue on the
+    /// original schedule.
+    Skip,
+    /// Fire once for all of the missed ticks, then restart the schedule
+    /// a full period after the tick was consumed.
+    Delay,
+}
+
+/// A `Ticker` produces a new `Signal` for every tick of a fixed period.
+///
+/// The first tick is due one period after the `Ticker` was created. Each
+/// call to `signal` consumes a tick, so a `Ticker` is normally used by
+/// adding `ticker.signal()` back into a `Select` every time it fires.
+pub struct Ticker {
+    period: Duration,
+    missed: MissedTick,
+    next: Mutex<Instant>,
+}
+
+impl Ticker {
+    /// Create a `Ticker` that will `Burst` to catch up with missed ticks.
+    pub fn new(period: Duration) -> Ticker {
+        Ticker::with_missed_tick(period, MissedTick::Burst)
+    }
+
+    /// Create a `Ticker` with the supplied missed tick behaviour.
+    pub fn with_missed_tick(period: Duration, missed: MissedTick) -> Ticker {
+        assert!(period > Duration::from_secs(0), "a Ticker period must be non-zero");
+        Ticker {
+            period,
+            missed,
+            next: Mutex::new(Instant::now() + period),
+        }
+    }
+
+    /// The period between ticks
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// What the `Ticker` does with missed ticks
+    pub fn missed_tick(&self) -> MissedTick {
+        self.missed
+    }
+}
+
+impl Signals for Ticker {
+    fn signal(&self) -> Signal {
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap();
+        let deadline = *next;
+
+        *next = if deadline >= now {
+            deadline + self.period
+        } else {
+            match self.missed {
+                MissedTick::Burst => deadline + self.period,
+                MissedTick::Skip => {
+                    let period = self.period.as_nanos();
+                    let missed = (now - deadline).as_nanos() / period + 1;
+                    deadline + Duration::from_nanos((missed * period) as u64)
+                }
+                MissedTick::Delay => now + self.period,
+            }
+        };
+
+        at(deadline)
+    }
+}
@@ -0,0 +1,56 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! Spans and events for the `tracing` feature. Everything is recorded
+//! under the `pulse` target with the signal's `id` and, if it has one,
+//! its `name`.
+
+use tracing::{debug, trace, trace_span};
+use tracing::span::EnteredSpan;
+
+use {Inner, Signal, SignalState, WaitError};
+
+pub(crate) fn created(inner: &Inner) {
+    trace!(target: "pulse", id = inner.id.0, name = inner.name, "signal created");
+}
+
+pub(crate) fn pulsed(inner: &Inner) {
+    trace!(target: "pulse", id = inner.id.0, name = inner.name, "pulsed");
+}
+
+pub(crate) fn dropped(inner: &Inner, state: SignalState) {
+    debug!(target: "pulse",
+           id = inner.id.0,
+           name = inner.name,
+           state = ?state,
+           "pulse dropped");
+}
+
+/// Enter a span that lasts until the wait on `inner` returns
+pub(crate) fn wait(inner: &Inner) -> EnteredSpan {
+    let span = trace_span!(target: "pulse", "wait", id = inner.id.0, name = inner.name);
+    let span = span.entered();
+    trace!(target: "pulse", "wait started");
+    span
+}
+
+pub(crate) fn waited(res: &Result<(), WaitError>) {
+    trace!(target: "pulse", result = ?res, "wait finished");
+}
+
+pub(crate) fn ready(signal: &Signal) {
+    let inner = signal.inner();
+    trace!(target: "pulse", id = inner.id.0, name = inner.name, "select ready");
+}
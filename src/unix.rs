@@ -0,0 +1,274 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! Signals that are pulsed when the process receives a Unix signal.
+//!
+//! The first time a Unix signal is listened for, a handler is installed
+//! for it that writes the signal number to a pipe. This is all that
+//! is safe to do inside of a signal handler, a background thread reads
+//! the pipe and pulses everything that is listening. The handler
+//! replaces the default action, so a `SIGTERM` that is listened for no
+//! longer terminates the process.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::thread;
+
+use libc;
+
+use background::fire;
+use {Pulse, Signal, Signals};
+
+pub use libc::{SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGUSR1, SIGUSR2, SIGWINCH};
+
+/// The write end of the pipe, read by the handler
+static WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+#[cfg(any(target_os = "linux", target_os = "emscripten"))]
+unsafe fn errno() -> *mut libc::c_int {
+    libc::__errno_location()
+}
+
+#[cfg(any(target_os = "android", target_os = "openbsd", target_os = "netbsd"))]
+unsafe fn errno() -> *mut libc::c_int {
+    libc::__errno()
+}
+
+#[cfg(any(target_os = "macos",
+          target_os = "ios",
+          target_os = "freebsd",
+          target_os = "dragonfly"))]
+unsafe fn errno() -> *mut libc::c_int {
+    libc::__error()
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+unsafe fn errno() -> *mut libc::c_int {
+    libc::___errno()
+}
+
+extern "C" fn handler(signum: libc::c_int) {
+    let byte = signum as u8;
+    let fd = WRITE_FD.load(Ordering::Relaxed);
+    // The interrupted code may be about to read `errno`, so a failed
+    // write must not change it. If the pipe is full the byte is lost,
+    // but there are already deliveries waiting to be read.
+    unsafe {
+        let saved = *errno();
+        libc::write(fd, &byte as *const u8 as *const libc::c_void, 1);
+        *errno() = saved;
+    }
+}
+
+#[derive(Default)]
+struct Listener {
+    missed: usize,
+    pulses: VecDeque<Pulse>,
+}
+
+#[derive(Default)]
+struct Slot {
+    once: Vec<Pulse>,
+    listeners: Vec<Weak<Mutex<Listener>>>,
+}
+
+struct Registry {
+    slots: Mutex<HashMap<libc::c_int, Slot>>,
+}
+
+impl Registry {
+    /// Lock the slot for `signum`, installing the handler if this is
+    /// the first time it is used.
+    fn with_slot<F, T>(&self, signum: libc::c_int, f: F) -> io::Result<T>
+        where F: FnOnce(&mut Slot) -> T
+    {
+        let mut slots = self.slots.lock().unwrap();
+        if !slots.contains_key(&signum) {
+            install(signum)?;
+        }
+        Ok(f(slots.entry(signum).or_default()))
+    }
+
+    fn run(&self, fd: libc::c_int) {
+        let mut buf = [0u8; 64];
+        let mut ready = Vec::new();
+        let mut upgraded = Vec::new();
+        loop {
+            let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if n <= 0 {
+                // EINTR, the write end is never closed
+                continue;
+            }
+
+            {
+                let mut slots = self.slots.lock().unwrap();
+                for &signum in &buf[..n as usize] {
+                    let slot = match slots.get_mut(&(signum as libc::c_int)) {
+                        Some(slot) => slot,
+                        None => continue,
+                    };
+                    ready.append(&mut slot.once);
+                    slot.listeners.retain(|l| {
+                        let listener = match l.upgrade() {
+                            Some(listener) => listener,
+                            None => return false,
+                        };
+                        // The abandoned pulses are dropped by `fire`
+                        let mut guard = listener.lock().unwrap();
+                        let mut found = false;
+                        while let Some(pulse) = guard.pulses.pop_front() {
+                            found = !pulse.is_abandoned();
+                            ready.push(pulse);
+                            if found {
+                                break;
+                            }
+                        }
+                        if !found {
+                            guard.missed += 1;
+                        }
+                        drop(guard);
+                        // This may be the last reference to the listener
+                        upgraded.push(listener);
+                        true
+                    });
+                }
+            }
+
+            fire(ready.drain(..));
+            upgraded.clear();
+        }
+    }
+}
+
+fn install(signum: libc::c_int) -> io::Result<()> {
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(signum, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn cloexec(fd: libc::c_int) -> io::Result<()> {
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn start() -> io::Result<&'static Registry> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    cloexec(fds[0])?;
+    cloexec(fds[1])?;
+    // The handler must never block
+    if unsafe { libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let registry: &'static Registry = Box::leak(Box::new(Registry {
+        slots: Mutex::new(HashMap::new()),
+    }));
+    thread::Builder::new()
+        .name("pulse-signals".into())
+        .spawn(move || registry.run(fds[0]))?;
+    WRITE_FD.store(fds[1], Ordering::Relaxed);
+    Ok(registry)
+}
+
+fn registry() -> io::Result<&'static Registry> {
+    static REGISTRY: OnceLock<Result<&'static Registry, i32>> = OnceLock::new();
+
+    match *REGISTRY.get_or_init(|| start().map_err(|e| e.raw_os_error().unwrap_or(0))) {
+        Ok(registry) => Ok(registry),
+        Err(errno) => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+/// Create a `Signal` that will be pulsed the next time the process
+/// receives `signum`. This fails if a handler cannot be installed for
+/// `signum`, such as for `SIGKILL`.
+pub fn on_signal(signum: libc::c_int) -> io::Result<Signal> {
+    let (signal, pulse) = Signal::new();
+    let swept: Vec<Pulse> = registry()?.with_slot(signum, |slot| {
+        let (mut live, abandoned): (Vec<Pulse>, _) = mem::take(&mut slot.once)
+            .into_iter()
+            .partition(|p| !p.is_abandoned());
+        live.push(pulse);
+        slot.once = live;
+        abandoned
+    })?;
+    // Dropped after `with_slot` has released the lock
+    drop(swept);
+    Ok(signal)
+}
+
+/// Listens for every delivery of a Unix signal.
+///
+/// Each call to `signal` consumes one delivery, so like a
+/// `timer::Ticker` it is normally used by adding `listener.signal()`
+/// back into a `Select` every time it fires. Deliveries that arrive
+/// while nothing is waiting are kept, but the operating system may
+/// merge deliveries that arrive close together into one.
+pub struct SignalListener {
+    signum: libc::c_int,
+    listener: Arc<Mutex<Listener>>,
+}
+
+impl SignalListener {
+    /// Start listening for `signum`
+    pub fn new(signum: libc::c_int) -> io::Result<SignalListener> {
+        let listener = Arc::new(Mutex::new(Listener::default()));
+        registry()?.with_slot(signum, |slot| {
+            slot.listeners.push(Arc::downgrade(&listener));
+        })?;
+        Ok(SignalListener { signum, listener })
+    }
+
+    /// The signal number that is listened for
+    pub fn signum(&self) -> libc::c_int {
+        self.signum
+    }
+}
+
+impl Signals for SignalListener {
+    fn signal(&self) -> Signal {
+        let mut listener = self.listener.lock().unwrap();
+        if listener.missed > 0 {
+            listener.missed -= 1;
+            return Signal::pulsed();
+        }
+
+        let (signal, pulse) = Signal::new();
+        let (mut live, swept): (VecDeque<Pulse>, _) = mem::take(&mut listener.pulses)
+            .into_iter()
+            .partition(|p| !p.is_abandoned());
+        live.push_back(pulse);
+        listener.pulses = live;
+        drop(listener);
+        drop(swept);
+        signal
+    }
+}
@@ -0,0 +1,183 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! The waitlist of a `Signal`.
+//!
+//! This is an intrusive doubly-linked list of `Waiting` nodes protected
+//! by a spinlock. Every node is reference counted, the list holds one
+//! reference for as long as the node is linked and whoever registered
+//! the waiter may hold another to unlink it again in constant time.
+//!
+//! Waking never happens while the lock is held. The list is detached
+//! under the lock and the nodes are woken afterwards, at which point the
+//! waker has exclusive access to them.
+
+use alloc::sync::Arc;
+use core::cell::Cell;
+use core::ptr;
+
+use sync::{spin_loop, AtomicBool, Ordering};
+use Waiting;
+
+/// The links of a `Waiting` node. These are only read or written
+/// while holding the lock of the list the node was pushed onto.
+pub struct Link {
+    prev: Cell<*const Waiting>,
+    next: Cell<*const Waiting>,
+    linked: Cell<bool>,
+}
+
+impl Link {
+    pub fn new() -> Link {
+        Link {
+            prev: Cell::new(ptr::null()),
+            next: Cell::new(ptr::null()),
+            linked: Cell::new(false),
+        }
+    }
+}
+
+pub struct WaitList {
+    locked: AtomicBool,
+    head: Cell<*const Waiting>,
+}
+
+impl WaitList {
+    pub fn new() -> WaitList {
+        WaitList {
+            locked: AtomicBool::new(false),
+            head: Cell::new(ptr::null()),
+        }
+    }
+
+    pub fn lock(&self) -> Guard<'_> {
+        // The lock is only held for a few pointer writes, so contention
+        // is short lived and a plain compare and swap loop is enough.
+        while self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err() {
+            spin_loop();
+        }
+        Guard { list: self }
+    }
+}
+
+impl Drop for WaitList {
+    fn drop(&mut self) {
+        drop(self.lock().take());
+    }
+}
+
+pub struct Guard<'a> {
+    list: &'a WaitList,
+}
+
+impl<'a> Guard<'a> {
+    /// Link a waiter onto the head of the list
+    pub fn push(&self, waiter: Arc<Waiting>) {
+        let head = self.list.head.get();
+        let node = Arc::into_raw(waiter);
+        unsafe {
+            (*node).link.next.set(head);
+            (*node).link.linked.set(true);
+            if !head.is_null() {
+                (*head).link.prev.set(node);
+            }
+        }
+        self.list.head.set(node);
+    }
+
+    /// Unlink a waiter if it is still on the list
+    pub fn remove(&self, waiter: &Arc<Waiting>) {
+        let link = &waiter.link;
+        if !link.linked.get() {
+            return;
+        }
+
+        let (prev, next) = (link.prev.get(), link.next.get());
+        unsafe {
+            if prev.is_null() {
+                self.list.head.set(next);
+            } else {
+                (*prev).link.next.set(next);
+            }
+            if !next.is_null() {
+                (*next).link.prev.set(prev);
+            }
+            link.linked.set(false);
+            drop(Arc::from_raw(&**waiter as *const Waiting));
+        }
+    }
+
+    /// Visit every waiter that is on the list
+    #[cfg(feature = "debug-registry")]
+    pub fn for_each<F>(&self, mut f: F)
+        where F: FnMut(&Waiting)
+    {
+        let mut node = self.list.head.get();
+        while !node.is_null() {
+            unsafe {
+                f(&*node);
+                node = (*node).link.next.get();
+            }
+        }
+    }
+
+    /// Detach every waiter from the list
+    pub fn take(&self) -> Detached {
+        let head = self.list.head.replace(ptr::null());
+        let mut node = head;
+        while !node.is_null() {
+            unsafe {
+                (*node).link.linked.set(false);
+                node = (*node).link.next.get();
+            }
+        }
+        Detached { head }
+    }
+}
+
+impl<'a> Drop for Guard<'a> {
+    fn drop(&mut self) {
+        self.list.locked.store(false, Ordering::Release);
+    }
+}
+
+/// The waiters that were on the list when it was taken. Nothing else
+/// can reach these nodes' links anymore, so they can be walked without
+/// holding the lock.
+pub(crate) struct Detached {
+    head: *const Waiting,
+}
+
+impl Iterator for Detached {
+    type Item = Arc<Waiting>;
+
+    fn next(&mut self) -> Option<Arc<Waiting>> {
+        if self.head.is_null() {
+            return None;
+        }
+        unsafe {
+            let node = Arc::from_raw(self.head);
+            self.head = node.link.next.get();
+            Some(node)
+        }
+    }
+}
+
+impl Drop for Detached {
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
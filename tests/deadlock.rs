@@ -0,0 +1,67 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![cfg(feature = "deadlock-detection")]
+
+extern crate pulse;
+
+use std::thread;
+use pulse::*;
+
+#[test]
+fn self_deadlock() {
+    let (s, p) = Signal::new();
+    p.claim();
+    assert_eq!(s.clone().wait(), Err(WaitError::Deadlock));
+
+    p.pulse();
+    assert_eq!(s.wait(), Ok(()));
+}
+
+#[test]
+fn moved_pulse() {
+    let (s, p) = Signal::new();
+    let (claimed, claim) = Signal::new();
+    p.claim();
+    thread::spawn(move || {
+        p.claim();
+        claim.pulse();
+        thread::sleep(std::time::Duration::from_millis(10));
+        p.pulse();
+    });
+    claimed.wait().unwrap();
+    assert_eq!(s.wait(), Ok(()));
+}
+
+#[test]
+fn cycle() {
+    let (s0, p0) = Signal::new();
+    let (s1, p1) = Signal::new();
+    p0.claim();
+
+    let t = thread::spawn(move || {
+        p1.claim();
+        let res = s0.wait();
+        drop(p1);
+        res
+    });
+    let res = s1.wait();
+    drop(p0);
+
+    // whichever thread blocked second closed the cycle, once it gives
+    // up its pulse the other one is woken
+    let mut results = vec![res, t.join().unwrap()];
+    results.sort_by_key(|r| *r == Err(WaitError::Deadlock));
+    assert_eq!(results, vec![Err(WaitError::Dropped), Err(WaitError::Deadlock)]);
+}
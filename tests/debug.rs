@@ -0,0 +1,68 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![cfg(feature = "debug-registry")]
+
+extern crate pulse;
+
+use pulse::debug::{self, SignalInfo, WaiterKind};
+use pulse::*;
+
+fn find(id: SignalId) -> Option<SignalInfo> {
+    debug::dump().into_iter().find(|info| info.id == id)
+}
+
+#[test]
+fn dump_pending() {
+    let line = line!() + 1;
+    let (s, p) = Signal::named("dump_pending");
+    let info = find(s.id()).unwrap();
+    assert_eq!(info.name, Some("dump_pending"));
+    assert_eq!(info.location.file(), file!());
+    assert_eq!(info.location.line(), line);
+    assert!(info.waiters.is_empty());
+    assert!(info.to_string().contains("dump_pending"));
+
+    p.pulse();
+    assert!(find(s.id()).is_none());
+}
+
+#[test]
+fn dump_waiters() {
+    let (s, p) = Signal::new();
+    let id = s.id();
+    let mut select = Select::new();
+    select.add(s.clone());
+    let _barrier = Barrier::new(std::slice::from_ref(&s));
+    s.callback(|| ());
+
+    let info = find(id).unwrap();
+    assert_eq!(info.name, None);
+    let mut waiters = info.waiters;
+    waiters.sort_by_key(|w| *w as usize);
+    assert_eq!(waiters,
+               vec![WaiterKind::Select, WaiterKind::Barrier, WaiterKind::Callback]);
+
+    drop(p);
+    assert!(find(id).is_none());
+}
+
+#[test]
+fn dump_freed() {
+    let (s, p) = Signal::new();
+    let id = s.id();
+    drop(s);
+    drop(p);
+    assert!(find(id).is_none());
+}
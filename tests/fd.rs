@@ -0,0 +1,79 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+#![cfg(target_os = "linux")]
+
+extern crate libc;
+extern crate pulse;
+
+use std::os::unix::io::AsRawFd;
+use std::thread;
+use pulse::*;
+
+/// Poll `fd` for reading, returns true if it is readable
+fn readable<T: AsRawFd>(fd: &T, timeout_ms: i32) -> bool {
+    let mut pfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let n = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+    assert!(n >= 0);
+    n == 1 && pfd.revents & libc::POLLIN != 0
+}
+
+#[test]
+fn pulsed() {
+    let (s, p) = Signal::new();
+    let fd = s.as_event_fd().unwrap();
+    assert!(!readable(&fd, 0));
+    p.pulse();
+    assert!(readable(&fd, 0));
+    assert!(readable(&fd, 0));
+    assert_eq!(fd.state(), SignalState::Pulsed);
+}
+
+#[test]
+fn already_pulsed() {
+    let fd = FdSignal::new(Signal::pulsed()).unwrap();
+    assert!(readable(&fd, 0));
+}
+
+#[test]
+fn dropped() {
+    let (s, p) = Signal::new();
+    let fd = FdSignal::new(s).unwrap();
+    drop(p);
+    assert!(readable(&fd, 0));
+    assert_eq!(fd.state(), SignalState::Dropped);
+}
+
+#[test]
+fn pulsed_thread() {
+    let (s, p) = Signal::new();
+    let fd = FdSignal::new(s).unwrap();
+    let t = thread::spawn(move || p.pulse());
+    assert!(readable(&fd, 5000));
+    t.join().unwrap();
+}
+
+#[test]
+fn drop_before_pulse() {
+    let (s, p) = Signal::new();
+    let fd = s.as_event_fd().unwrap();
+    drop(fd);
+    p.pulse();
+    s.wait().unwrap();
+}
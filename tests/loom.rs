@@ -0,0 +1,167 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Model checked tests of the `Signal` state machine, run them with
+//!
+//!     RUSTFLAGS="--cfg loom" cargo test --release --test loom
+//!
+//! The preemption bound defaults to 2 (1 for `clone_wait`) and can be
+//! raised with the `LOOM_MAX_PREEMPTIONS` environment variable. The TLS
+//! scheduler is not visible to loom, so these wait by calling
+//! `ThreadScheduler` directly.
+
+#![cfg(loom)]
+
+extern crate loom;
+extern crate pulse;
+
+use loom::thread;
+use pulse::*;
+
+fn model<F>(f: F)
+    where F: Fn() + Sync + Send + 'static
+{
+    model_bounded(2, f)
+}
+
+fn model_bounded<F>(bound: usize, f: F)
+    where F: Fn() + Sync + Send + 'static
+{
+    let mut builder = loom::model::Builder::new();
+    if builder.preemption_bound.is_none() {
+        builder.preemption_bound = Some(bound);
+    }
+    // waiters spin on the waitlist lock, which makes for long paths
+    builder.max_branches = 10_000;
+    builder.check(f);
+}
+
+#[test]
+fn pulse_wait() {
+    model(|| {
+        let (s, p) = Signal::new();
+        let t = thread::spawn(move || p.pulse());
+        assert_eq!(ThreadScheduler.wait(s), Ok(()));
+        t.join().unwrap();
+    });
+}
+
+#[test]
+fn drop_wait() {
+    model(|| {
+        let (s, p) = Signal::new();
+        let t = thread::spawn(move || drop(p));
+        assert_eq!(ThreadScheduler.wait(s), Err(WaitError::Dropped));
+        t.join().unwrap();
+    });
+}
+
+#[test]
+fn clone_wait() {
+    // Three threads contending on the waitlist lock is out of reach of
+    // the default bound, raise it by hand when touching the waitlist.
+    model_bounded(1, || {
+        let (s0, p) = Signal::new();
+        let s1 = s0.clone();
+        // A waiter can be unparked after it has stopped waiting, which
+        // loom's `join` does not tolerate, so only spawned threads wait.
+        let t0 = thread::spawn(move || ThreadScheduler.wait(s0));
+        let t1 = thread::spawn(move || ThreadScheduler.wait(s1));
+        p.pulse();
+        assert_eq!(t0.join().unwrap(), Ok(()));
+        assert_eq!(t1.join().unwrap(), Ok(()));
+    });
+}
+
+#[test]
+fn clone_drop_pulse() {
+    model(|| {
+        let (s0, p) = Signal::new();
+        let s1 = s0.clone();
+        let t0 = thread::spawn(move || drop(s0));
+        let t1 = thread::spawn(move || p.pulse());
+        assert!(s1.clone().state() != SignalState::Dropped);
+        drop(s1);
+        t0.join().unwrap();
+        t1.join().unwrap();
+    });
+}
+
+#[test]
+fn drop_all_signals_then_pulse() {
+    model(|| {
+        let (s, p) = Signal::new();
+        let t = thread::spawn(move || drop(s));
+        p.pulse();
+        t.join().unwrap();
+    });
+}
+
+#[test]
+fn weak_upgrade_drop() {
+    model(|| {
+        let (s, p) = Signal::new();
+        let w = s.downgrade();
+        let t = thread::spawn(move || {
+            drop(s);
+            p.pulse();
+        });
+        if let Some(s) = w.upgrade() {
+            assert!(s.state() != SignalState::Dropped);
+        }
+        t.join().unwrap();
+        assert!(w.upgrade().is_none());
+        assert_eq!(w.state(), None);
+    });
+}
+
+#[test]
+fn closed_drop() {
+    model(|| {
+        let (s, mut p) = Signal::new();
+        let t = thread::spawn(move || drop(s));
+        let c = p.closed();
+        t.join().unwrap();
+        assert_eq!(c.state(), SignalState::Pulsed);
+        drop(p);
+    });
+}
+
+#[test]
+fn closed_drop_pulse() {
+    model(|| {
+        let (s, mut p) = Signal::new();
+        let c = p.closed();
+        let t = thread::spawn(move || drop(s));
+        p.pulse();
+        t.join().unwrap();
+        assert!(c.state() != SignalState::Pending);
+    });
+}
+
+#[test]
+fn select_remove_pulse() {
+    model(|| {
+        let mut select = Select::new();
+        let (s, p) = Signal::new();
+        let id = select.add(s);
+
+        let t = thread::spawn(move || p.pulse());
+        select.remove(id).unwrap();
+        assert!(select.try_next().is_none());
+        t.join().unwrap();
+        // the pulse may have pushed its key after it was removed
+        assert!(select.try_next().is_none());
+    });
+}
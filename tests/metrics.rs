@@ -0,0 +1,67 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![cfg(feature = "metrics")]
+
+extern crate pulse;
+
+use std::thread;
+use std::time::Duration;
+use pulse::*;
+
+// The counters are process wide and the tests run in parallel, so
+// these only check that they went up by at least the expected amount.
+
+#[test]
+fn pulse_and_drop() {
+    let before = metrics::snapshot();
+    let (s0, p0) = Signal::new();
+    let (s1, p1) = Signal::new();
+    p0.pulse();
+    p1.cancel();
+    drop((s0, s1));
+
+    let after = metrics::snapshot();
+    assert!(after.pulsed > before.pulsed);
+    assert!(after.dropped > before.dropped);
+}
+
+#[test]
+fn parked_wait() {
+    let before = metrics::snapshot();
+    let (s, p) = Signal::new();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        p.pulse();
+    });
+    ThreadScheduler.wait(s).unwrap();
+
+    let after = metrics::snapshot();
+    assert!(after.waits > before.waits);
+    assert!(after.parks > before.parks);
+    assert!(after.wakes > before.wakes);
+    assert!(after.latency.count() > before.latency.count());
+    assert!(after.latency.quantile(1.0).is_some());
+}
+
+#[test]
+fn histogram_quantile() {
+    let snapshot = metrics::snapshot();
+    let count = snapshot.latency.count();
+    assert_eq!(snapshot.latency.buckets().len(), metrics::BUCKETS);
+    assert_eq!(count == 0, snapshot.latency.quantile(0.5).is_none());
+    if count > 0 {
+        assert!(snapshot.latency.quantile(0.5) <= snapshot.latency.quantile(0.99));
+    }
+}
@@ -0,0 +1,57 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+#![cfg(unix)]
+
+extern crate pulse;
+
+use std::process::Command;
+use pulse::*;
+
+#[test]
+fn exited() {
+    let mut child = Command::new("sh").args(["-c", "sleep 0.05; exit 3"]).spawn().unwrap();
+    let s = process::exited(&mut child).unwrap();
+    s.wait().unwrap();
+
+    // the child was left for us to reap
+    assert_eq!(child.try_wait().unwrap().and_then(|s| s.code()), Some(3));
+}
+
+#[test]
+fn already_waited() {
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    assert_eq!(process::exited(&mut child).unwrap().state(), SignalState::Pulsed);
+}
+
+#[test]
+fn select_first() {
+    let mut slow = Command::new("sleep").arg("10").spawn().unwrap();
+    let mut fast = Command::new("true").spawn().unwrap();
+
+    let mut select = SelectMap::new();
+    select.add(process::exited(&mut slow).unwrap(), "slow");
+    select.add(process::exited(&mut fast).unwrap(), "fast");
+
+    let (_, name) = select.next().unwrap();
+    assert_eq!(name, "fast");
+    assert!(fast.wait().unwrap().success());
+
+    slow.kill().unwrap();
+    let (_, name) = select.next().unwrap();
+    assert_eq!(name, "slow");
+    assert!(!slow.wait().unwrap().success());
+}
@@ -0,0 +1,112 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+#![cfg(target_os = "linux")]
+
+extern crate pulse;
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use pulse::*;
+
+#[test]
+fn readable() {
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    let s = reactor::readable(&a).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert!(s.is_pending());
+
+    b.write_all(b"x").unwrap();
+    s.wait().unwrap();
+    let mut buf = [0; 1];
+    a.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"x");
+}
+
+#[test]
+fn writable() {
+    let (a, _b) = UnixStream::pair().unwrap();
+    reactor::writable(&a).unwrap().wait().unwrap();
+}
+
+#[test]
+fn hang_up() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let s = reactor::readable(&a).unwrap();
+    drop(b);
+    s.wait().unwrap();
+}
+
+#[test]
+fn callback() {
+    let (a, mut b) = UnixStream::pair().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let s = reactor::readable(&a).unwrap();
+    let w = s.downgrade();
+    s.callback(move || tx.send(w.state()).unwrap());
+
+    // a new registration makes the reactor look for abandoned ones
+    let _other = reactor::readable(&a).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert!(rx.try_recv().is_err());
+
+    b.write_all(b"x").unwrap();
+    assert_eq!(rx.recv().unwrap(), Some(SignalState::Pulsed));
+}
+
+#[test]
+fn close_original() {
+    let (a, mut b) = UnixStream::pair().unwrap();
+    let s = reactor::readable(&a).unwrap();
+
+    // the reactor has its own copy of `a`, so reusing its number for
+    // a new descriptor cannot confuse it
+    drop(a);
+    let (_c, _d) = UnixStream::pair().unwrap();
+    thread::sleep(Duration::from_millis(20));
+    assert!(s.is_pending());
+
+    b.write_all(b"x").unwrap();
+    s.wait().unwrap();
+}
+
+#[test]
+fn select() {
+    let (a, mut b) = UnixStream::pair().unwrap();
+    let (c, _d) = UnixStream::pair().unwrap();
+
+    let mut select = Select::new();
+    let quiet = select.add(reactor::readable(&c).unwrap());
+    let loud = select.add(reactor::readable(&a).unwrap());
+    b.write_all(b"x").unwrap();
+
+    let s = select.next().unwrap();
+    assert_eq!(s.id(), loud);
+    assert!(select.remove(quiet).unwrap().is_pending());
+}
+
+#[test]
+fn many() {
+    let pairs: Vec<_> = (0..16).map(|_| UnixStream::pair().unwrap()).collect();
+    let signals: Vec<_> = pairs.iter().map(|(a, _)| reactor::readable(a).unwrap()).collect();
+    for (_, b) in &pairs {
+        let mut b = b;
+        b.write_all(b"x").unwrap();
+    }
+    Barrier::new(&signals).wait().unwrap();
+}
@@ -0,0 +1,65 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![cfg(target_os = "linux")]
+
+//! Lowering `RLIMIT_NOFILE` affects the whole process, so this has a
+//! test binary of its own.
+
+extern crate libc;
+extern crate pulse;
+
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use pulse::*;
+
+fn set_nofile(limit: &libc::rlimit) {
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, limit) }, 0);
+}
+
+#[test]
+fn over_nofile() {
+    let mut old = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut old) }, 0);
+
+    let (a, _b) = UnixStream::pair().unwrap();
+    let (c, mut d) = UnixStream::pair().unwrap();
+    let quiet: Vec<Signal> = (0..32).map(|_| reactor::readable(&a).unwrap()).collect();
+    let loud = reactor::readable(&c).unwrap();
+
+    // the next time the reactor polls it has more descriptors than
+    // the limit allows, `loud` makes sure that happens even if it was
+    // already polling all of them
+    set_nofile(&libc::rlimit {
+        rlim_cur: 16,
+        rlim_max: old.rlim_max,
+    });
+    d.write_all(b"x").unwrap();
+    let _ = loud.wait();
+
+    // the newest registrations are the ones that are failed
+    let newest = quiet.last().unwrap().clone();
+    assert_eq!(newest.wait(), Err(WaitError::Failed(libc::EINVAL as u32)));
+    assert!(quiet[0].is_pending());
+    set_nofile(&old);
+
+    // the reactor keeps working once the limit is back
+    let (e, mut f) = UnixStream::pair().unwrap();
+    let s = reactor::readable(&e).unwrap();
+    f.write_all(b"x").unwrap();
+    s.wait().unwrap();
+}
@@ -0,0 +1,247 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate pulse;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use pulse::*;
+
+#[derive(Debug)]
+struct Counting(Arc<AtomicUsize>);
+
+impl Scheduler for Counting {
+    fn wait(&self, signal: Signal) -> Result<(), WaitError> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ThreadScheduler.wait(signal)
+    }
+
+    fn wait_timeout_ms(&self, signal: Signal, ms: u32) -> Result<(), TimeoutError> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ThreadScheduler.wait_timeout_ms(signal, ms)
+    }
+}
+
+/// The number of waits seen by schedulers made by the default factory
+fn default_waits() -> &'static AtomicUsize {
+    static COUNT: OnceLock<Arc<AtomicUsize>> = OnceLock::new();
+    COUNT.get_or_init(|| {
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        set_default_scheduler_factory(move || Box::new(Counting(c.clone())));
+        count
+    })
+}
+
+fn pulse_later(t: Pulse) {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        t.pulse();
+    });
+}
+
+#[test]
+fn default_factory() {
+    let count = default_waits();
+    let before = count.load(Ordering::SeqCst);
+
+    let (p, t) = Signal::new();
+    pulse_later(t);
+    thread::spawn(move || p.wait().unwrap()).join().unwrap();
+    assert!(count.load(Ordering::SeqCst) > before);
+}
+
+#[test]
+fn thread_local_override() {
+    default_waits();
+    let count = Arc::new(AtomicUsize::new(0));
+    let local = count.clone();
+
+    let (p, t) = Signal::new();
+    pulse_later(t);
+    thread::spawn(move || {
+        swap_scheduler(Box::new(Counting(local)));
+        p.wait().unwrap();
+    }).join().unwrap();
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn spin_scheduler() {
+    let (p, t) = Signal::new();
+    pulse_later(t);
+    thread::spawn(move || {
+        swap_scheduler(Box::new(SpinScheduler::new(1000)));
+        p.wait().unwrap();
+    }).join().unwrap();
+}
+
+#[test]
+fn spin_scheduler_timeout() {
+    let (p, t) = Signal::new();
+    thread::spawn(move || {
+        swap_scheduler(Box::new(SpinScheduler::new(1000)));
+        assert_eq!(p.clone().wait_timeout_ms(10), Err(TimeoutError::Timeout));
+        t.pulse();
+        assert_eq!(p.wait_timeout_ms(10), Ok(()));
+    }).join().unwrap();
+}
+
+#[test]
+fn spin_scheduler_result() {
+    let sched = SpinScheduler::new(10);
+
+    let (p, t) = Signal::new();
+    t.fail(3);
+    assert_eq!(sched.wait(p.clone()), Err(WaitError::Failed(3)));
+    assert_eq!(sched.wait_timeout_ms(p, 10), Err(TimeoutError::Error(WaitError::Failed(3))));
+
+    let (p, t) = Signal::new();
+    thread::spawn(move || t.pulse());
+    assert_eq!(sched.wait(p), Ok(()));
+}
+
+/// A stand-in for a green thread, it can only be woken through `Unpark`
+#[derive(Debug, Default)]
+struct Task {
+    woken: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Unpark for Task {
+    fn unpark(&self) {
+        *self.woken.lock().unwrap() = true;
+        self.cond.notify_one();
+    }
+}
+
+#[derive(Debug, Default)]
+struct TaskScheduler {
+    task: Arc<Task>,
+}
+
+impl Scheduler for TaskScheduler {
+    fn wait(&self, signal: Signal) -> Result<(), WaitError> {
+        let registration = signal.register(self.unparker());
+        let mut woken = self.task.woken.lock().unwrap();
+        while !*woken {
+            woken = self.task.cond.wait(woken).unwrap();
+        }
+        *woken = false;
+        drop(woken);
+        signal.unregister(registration);
+        assert!(!signal.is_pending());
+        ThreadScheduler.wait(signal)
+    }
+
+    fn wait_timeout_ms(&self, signal: Signal, ms: u32) -> Result<(), TimeoutError> {
+        let registration = signal.register(self.unparker());
+        let mut woken = self.task.woken.lock().unwrap();
+        let deadline = Instant::now() + Duration::from_millis(ms as u64);
+        while !*woken {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            woken = self.task.cond.wait_timeout(woken, deadline - now).unwrap().0;
+        }
+        drop(woken);
+        signal.unregister(registration);
+        // a pulse racing with the timeout may have woken the task late
+        *self.task.woken.lock().unwrap() = false;
+        if signal.is_pending() {
+            return Err(TimeoutError::Timeout);
+        }
+        ThreadScheduler.wait(signal).map_err(TimeoutError::Error)
+    }
+
+    fn unparker(&self) -> Arc<dyn Unpark> {
+        self.task.clone()
+    }
+}
+
+#[test]
+fn custom_unpark_select() {
+    let (p0, t0) = Signal::new();
+    let (p1, _t1) = Signal::new();
+    pulse_later(t0);
+    thread::spawn(move || {
+        swap_scheduler(Box::new(TaskScheduler::default()));
+        let mut select = Select::new();
+        let id = select.add(p0);
+        select.add(p1);
+        assert_eq!(select.next().unwrap().id(), id);
+    }).join().unwrap();
+}
+
+#[test]
+fn custom_unpark_timeout() {
+    thread::spawn(move || {
+        swap_scheduler(Box::new(TaskScheduler::default()));
+
+        let (p, _t) = Signal::new();
+        assert_eq!(p.wait_timeout_ms(10), Err(TimeoutError::Timeout));
+
+        let (p, t) = Signal::new();
+        pulse_later(t);
+        assert_eq!(p.wait_timeout_ms(10_000), Ok(()));
+
+        let (p, t) = Signal::new();
+        t.fail(1);
+        assert_eq!(p.wait_timeout_ms(10), Err(TimeoutError::Error(WaitError::Failed(1))));
+    }).join().unwrap();
+}
+
+#[test]
+fn custom_unpark_barrier() {
+    let (p0, t0) = Signal::new();
+    let (p1, t1) = Signal::new();
+    pulse_later(t0);
+    pulse_later(t1);
+    thread::spawn(move || {
+        swap_scheduler(Box::new(TaskScheduler::default()));
+        Barrier::new(&[p0, p1]).wait().unwrap();
+    }).join().unwrap();
+}
+
+#[test]
+fn unregister() {
+    let (p, t) = Signal::new();
+    let task = Arc::new(Task::default());
+    let registration = p.register(task.clone());
+    p.unregister(registration);
+    t.pulse();
+    assert!(!*task.woken.lock().unwrap());
+}
+
+#[test]
+fn unregister_clone() {
+    let (p, t) = Signal::new();
+    let task = Arc::new(Task::default());
+    let registration = p.register(task.clone());
+    p.clone().unregister(registration);
+    t.pulse();
+    assert!(!*task.woken.lock().unwrap());
+}
+
+#[test]
+#[should_panic]
+fn unregister_other_signal() {
+    let (a, _pa) = Signal::new();
+    let (b, _pb) = Signal::new();
+    let task = Arc::new(Task::default());
+    b.unregister(a.register(task));
+}
@@ -142,3 +142,49 @@ fn select_remove() {
     select.remove(id0).unwrap();
     assert_eq!(id1, select.next().unwrap().id());
 }
+
+#[test]
+fn select_duplicate() {
+    let (p, t) = Signal::new();
+
+    let mut select = Select::new();
+    let id0 = select.add(p.clone());
+    let id1 = select.add(p);
+    assert_eq!(id0, id1);
+    assert_eq!(select.len(), 2);
+
+    t.pulse();
+    assert_eq!(id0, select.next().unwrap().id());
+    assert_eq!(id0, select.next().unwrap().id());
+    assert!(select.next().is_none());
+}
+
+#[test]
+fn select_remove_duplicate() {
+    let (p, t) = Signal::new();
+
+    let mut select = Select::new();
+    let id = select.add(p.clone());
+    select.add(p);
+
+    t.pulse();
+    select.remove(id).unwrap();
+    assert_eq!(id, select.try_next().unwrap().id());
+    assert!(select.try_next().is_none());
+    assert!(select.remove(id).is_none());
+}
+
+#[test]
+fn select_map_duplicate() {
+    let (p, t) = Signal::new();
+
+    let mut select = SelectMap::new();
+    select.add(p.clone(), 0);
+    select.add(p, 1);
+    assert_eq!(select.len(), 2);
+
+    t.pulse();
+    let mut values: Vec<_> = select.map(|(_, v)| v).collect();
+    values.sort();
+    assert_eq!(values, vec![0, 1]);
+}
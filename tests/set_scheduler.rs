@@ -0,0 +1,62 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+extern crate pulse;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use pulse::*;
+
+// `set_scheduler` is process wide, so this is kept out of
+// tests/scheduler.rs where it would see the other tests' waits.
+
+#[derive(Debug)]
+struct Counting(AtomicUsize);
+
+impl Scheduler for Counting {
+    fn wait(&self, signal: Signal) -> Result<(), WaitError> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ThreadScheduler.wait(signal)
+    }
+
+    fn wait_timeout_ms(&self, signal: Signal, ms: u32) -> Result<(), TimeoutError> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ThreadScheduler.wait_timeout_ms(signal, ms)
+    }
+}
+
+static COUNTING: Counting = Counting(AtomicUsize::new(0));
+
+fn wait_pending() {
+    let (p, t) = Signal::new();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        t.pulse();
+    });
+    p.wait().unwrap();
+}
+
+#[test]
+fn set_scheduler_with_std() {
+    // this thread has already created its scheduler
+    wait_pending();
+    set_scheduler(&COUNTING);
+    wait_pending();
+    assert_eq!(COUNTING.0.load(Ordering::SeqCst), 1);
+
+    thread::spawn(wait_pending).join().unwrap();
+    assert_eq!(COUNTING.0.load(Ordering::SeqCst), 2);
+}
@@ -81,6 +81,19 @@ fn false_positive_wake() {
     p.wait().unwrap();
 }
 
+#[test]
+fn ids_are_not_reused() {
+    let (s0, p0) = Signal::new();
+    let id0 = s0.id();
+    drop(s0);
+    drop(p0);
+
+    // the freed allocation is likely to be handed straight back out
+    let (s1, _p1) = Signal::new();
+    assert!(id0 != s1.id());
+    assert!(Signal::pulsed().id() != s1.id());
+}
+
 #[test]
 fn clone() {
     let (p0, t) = Signal::new();
@@ -152,3 +165,158 @@ fn test_timeout() {
     p.pulse();
     assert_eq!(s.clone().wait_timeout_ms(25), Ok(()));
 }
+
+#[test]
+fn weak_upgrade() {
+    let (s, p) = Signal::new();
+    let w = s.downgrade();
+    assert_eq!(w.id(), s.id());
+    assert_eq!(w.state(), Some(SignalState::Pending));
+
+    let s1 = w.upgrade().unwrap();
+    drop(s);
+    p.pulse();
+    assert_eq!(w.state(), Some(SignalState::Pulsed));
+    assert_eq!(s1.state(), SignalState::Pulsed);
+
+    drop(s1);
+    assert!(w.upgrade().is_none());
+    assert_eq!(w.state(), None);
+}
+
+#[test]
+fn weak_upgrade_while_pulse_alive() {
+    let (s, p) = Signal::new();
+    let w = s.downgrade();
+    drop(s);
+
+    // the pulse still holds a reference, so this can be upgraded
+    let s = w.upgrade().unwrap();
+    drop(p);
+    assert_eq!(s.state(), SignalState::Dropped);
+    drop(s);
+
+    let w1 = w.clone();
+    drop(w);
+    assert_eq!(w1.state(), None);
+}
+
+#[test]
+fn abandoned() {
+    let (s0, p) = Signal::new();
+    let s1 = s0.clone();
+    assert_eq!(p.waiter_count(), 2);
+    assert!(!p.is_abandoned());
+
+    drop(s0);
+    assert_eq!(p.waiter_count(), 1);
+    let w = s1.downgrade();
+    drop(s1);
+    assert_eq!(p.waiter_count(), 0);
+    assert!(p.is_abandoned());
+    drop(w);
+}
+
+#[test]
+fn closed() {
+    let (s0, mut p) = Signal::new();
+    let s1 = s0.clone();
+    let c0 = p.closed();
+    let c1 = p.closed();
+    assert!(c0.is_pending());
+
+    drop(s0);
+    assert!(c0.is_pending());
+    drop(s1);
+    assert_eq!(c0.state(), SignalState::Pulsed);
+    assert_eq!(c1.state(), SignalState::Pulsed);
+
+    // already abandoned
+    assert_eq!(p.closed().state(), SignalState::Pulsed);
+}
+
+#[test]
+fn callback_listens() {
+    let (s, mut p) = Signal::new();
+    let c = p.closed();
+    let (done, fired) = Signal::new();
+    s.callback(move || fired.pulse());
+    assert_eq!(p.waiter_count(), 1);
+    assert!(!p.is_abandoned());
+    assert!(c.is_pending());
+
+    p.pulse();
+    assert_eq!(done.state(), SignalState::Pulsed);
+    assert_eq!(c.state(), SignalState::Dropped);
+}
+
+#[test]
+fn barrier_listens() {
+    let (s, mut p) = Signal::new();
+    let c = p.closed();
+    let barrier = Barrier::new(&[s]);
+    assert_eq!(p.waiter_count(), 1);
+    assert!(!p.is_abandoned());
+    assert!(c.is_pending());
+
+    p.pulse();
+    assert_eq!(barrier.signal().state(), SignalState::Pulsed);
+    assert_eq!(c.state(), SignalState::Dropped);
+}
+
+#[test]
+fn closed_after_pulse() {
+    let (s, mut p) = Signal::new();
+    let c = p.closed();
+    p.pulse();
+    assert_eq!(c.state(), SignalState::Dropped);
+    drop(s);
+}
+
+#[test]
+fn closed_thread() {
+    let (s, mut p) = Signal::new();
+    let c = p.closed();
+    thread::spawn(move || drop(s));
+    c.wait().unwrap();
+    assert!(p.is_abandoned());
+}
+
+#[test]
+fn fail() {
+    let (s, p) = Signal::new();
+    let s1 = s.clone();
+    p.fail(7);
+    assert_eq!(s.state(), SignalState::Failed(7));
+    assert_eq!(s.wait(), Err(WaitError::Failed(7)));
+    assert_eq!(s1.wait_timeout_ms(10), Err(TimeoutError::Error(WaitError::Failed(7))));
+}
+
+#[test]
+fn cancel() {
+    let (s, p) = Signal::new();
+    thread::spawn(move || p.cancel());
+    assert_eq!(s.wait(), Err(WaitError::Cancelled));
+}
+
+#[test]
+fn panicked() {
+    let (s, p) = Signal::new();
+    let t = thread::spawn(move || {
+        let _p = p;
+        panic!("expected panic");
+    });
+    assert_eq!(s.wait(), Err(WaitError::Panicked));
+    assert!(t.join().is_err());
+}
+
+#[test]
+fn error_display() {
+    use std::error::Error;
+
+    assert_eq!(WaitError::Failed(3).to_string(), "the pulse failed with code 3");
+    let err = TimeoutError::Error(WaitError::Cancelled);
+    assert_eq!(err.to_string(), WaitError::Cancelled.to_string());
+    assert!(err.source().is_some());
+    assert!(TimeoutError::Timeout.source().is_none());
+}
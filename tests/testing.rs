@@ -0,0 +1,88 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate pulse;
+
+use std::thread;
+use std::time::Duration;
+use pulse::*;
+use pulse::testing::VirtualScheduler;
+
+fn wait_for_sleepers(sched: &VirtualScheduler, count: usize) {
+    while sched.sleepers() != count {
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn timeout() {
+    let sched = VirtualScheduler::new();
+    let (s, p) = Signal::new();
+
+    let local = sched.clone();
+    let waiter = thread::spawn(move || {
+        swap_scheduler(Box::new(local));
+        s.wait_timeout_ms(100)
+    });
+
+    wait_for_sleepers(&sched, 1);
+    sched.advance(Duration::from_millis(99));
+    assert_eq!(sched.sleepers(), 1);
+    sched.advance(Duration::from_millis(1));
+    assert_eq!(waiter.join().unwrap(), Err(TimeoutError::Timeout));
+    assert_eq!(sched.now(), Duration::from_millis(100));
+    drop(p);
+}
+
+#[test]
+fn pulsed_before_timeout() {
+    let sched = VirtualScheduler::new();
+    let (s, p) = Signal::new();
+
+    let local = sched.clone();
+    let waiter = thread::spawn(move || {
+        swap_scheduler(Box::new(local));
+        s.wait_timeout_ms(100)
+    });
+
+    wait_for_sleepers(&sched, 1);
+    p.pulse();
+    assert_eq!(waiter.join().unwrap(), Ok(()));
+    assert_eq!(sched.sleepers(), 0);
+}
+
+#[test]
+fn dropped_before_timeout() {
+    let sched = VirtualScheduler::new();
+    let (s, p) = Signal::new();
+    drop(p);
+
+    swap_scheduler(Box::new(sched.clone()));
+    assert_eq!(s.wait_timeout_ms(100),
+               Err(TimeoutError::Error(WaitError::Dropped)));
+}
+
+#[test]
+fn virtual_timeout_signal() {
+    let sched = VirtualScheduler::new();
+    let t0 = sched.timeout(Duration::from_millis(10));
+    let t1 = sched.timeout(Duration::from_millis(20));
+    assert!(sched.timeout(Duration::from_millis(0)).wait().is_ok());
+
+    sched.advance(Duration::from_millis(15));
+    assert!(!t0.is_pending());
+    assert!(t1.is_pending());
+    sched.advance(Duration::from_millis(5));
+    assert!(!t1.is_pending());
+}
@@ -0,0 +1,58 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+extern crate pulse;
+
+use std::time::Duration;
+use pulse::*;
+
+#[test]
+fn spawn() {
+    let (s, join) = pulse::thread::spawn(|| 7);
+    s.wait().unwrap();
+    assert_eq!(join.join().unwrap(), 7);
+}
+
+#[test]
+fn panicked() {
+    let (s, join) = pulse::thread::spawn(|| panic!("oh no"));
+    assert_eq!(s.wait(), Err(WaitError::Panicked));
+    assert!(join.join().is_err());
+}
+
+#[test]
+fn spawn_with() {
+    let builder = std::thread::Builder::new().name("worker".into());
+    let (s, join) = pulse::thread::spawn_with(builder, || {
+        std::thread::current().name().map(String::from)
+    }).unwrap();
+    s.wait().unwrap();
+    assert_eq!(join.join().unwrap().as_deref(), Some("worker"));
+}
+
+#[test]
+fn first_finished() {
+    let mut select = SelectMap::new();
+    let (s, slow) = pulse::thread::spawn(|| {
+        std::thread::sleep(Duration::from_millis(200));
+        "slow"
+    });
+    select.add(s, slow);
+    let (s, fast) = pulse::thread::spawn(|| "fast");
+    select.add(s, fast);
+
+    let order: Vec<_> = select.map(|(_, join)| join.join().unwrap()).collect();
+    assert_eq!(order, ["fast", "slow"]);
+}
@@ -0,0 +1,175 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate pulse;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use pulse::*;
+
+#[test]
+fn after() {
+    let start = Instant::now();
+    let s = timer::after(Duration::from_millis(10));
+    assert!(s.is_pending());
+    s.wait().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(10));
+}
+
+#[test]
+fn at_past() {
+    let s = timer::at(Instant::now() - Duration::from_millis(10));
+    assert!(!s.is_pending());
+}
+
+#[test]
+fn select_timeout() {
+    let (p, _t) = Signal::new();
+    let mut select = Select::new();
+    let _ = select.add(p);
+    let id = select.add(timer::after(Duration::from_millis(10)));
+    assert_eq!(id, select.next().unwrap().id());
+}
+
+#[test]
+fn ordering() {
+    let mut select = Select::new();
+    let id2 = select.add(timer::after(Duration::from_millis(30)));
+    let id0 = select.add(timer::after(Duration::from_millis(10)));
+    let id1 = select.add(timer::after(Duration::from_millis(20)));
+
+    assert_eq!(id0, select.next().unwrap().id());
+    assert_eq!(id1, select.next().unwrap().id());
+    assert_eq!(id2, select.next().unwrap().id());
+}
+
+#[test]
+fn dropped_timers() {
+    let weak: Vec<WeakSignal> = (0..500)
+        .map(|_| timer::after(Duration::from_secs(60)).downgrade())
+        .collect();
+
+    // adding timers sweeps the abandoned ones long before this is done
+    for _ in 0..500 {
+        drop(timer::after(Duration::from_secs(60)));
+    }
+    for w in &weak {
+        assert_eq!(w.state(), None);
+    }
+    timer::after(Duration::from_millis(10)).wait().unwrap();
+}
+
+#[test]
+fn callback_timer() {
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    let s = timer::after(Duration::from_millis(100));
+    let w = s.downgrade();
+    s.callback(move || tx.send((start.elapsed(), w.state())).unwrap());
+
+    let (elapsed, state) = rx.recv().unwrap();
+    assert!(elapsed >= Duration::from_millis(100));
+    assert_eq!(state, Some(SignalState::Pulsed));
+}
+
+#[test]
+fn barrier_timer() {
+    let start = Instant::now();
+    let barrier = Barrier::new(&[timer::after(Duration::from_millis(100))]);
+    assert_eq!(barrier.signal().wait(), Ok(()));
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn ticker() {
+    let start = Instant::now();
+    let ticker = timer::Ticker::new(Duration::from_millis(10));
+    for i in 1..4 {
+        ticker.wait().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(10 * i));
+    }
+}
+
+#[test]
+fn ticker_select() {
+    let (p, t) = Signal::new();
+    let ticker = timer::Ticker::new(Duration::from_millis(10));
+    let mut select = SelectMap::new();
+    select.add(p, "data");
+    select.add(ticker.signal(), "tick");
+
+    let mut ticks = 0;
+    let mut t = Some(t);
+    while let Some((_, what)) = select.next() {
+        match what {
+            "tick" if ticks < 3 => {
+                ticks += 1;
+                select.add(ticker.signal(), "tick");
+            }
+            "tick" => t.take().unwrap().pulse(),
+            _ => break,
+        }
+    }
+    assert_eq!(ticks, 3);
+}
+
+// The sleeps below can overshoot by any amount on a loaded machine, so
+// these only rely on the lower bound of the sleep and on the time that
+// was actually measured.
+
+#[test]
+fn ticker_burst() {
+    let start = Instant::now();
+    let ticker = timer::Ticker::new(Duration::from_millis(50));
+    thread::sleep(Duration::from_millis(175));
+
+    let mut fired = 0;
+    while !ticker.signal().is_pending() {
+        fired += 1;
+    }
+    let elapsed = start.elapsed();
+
+    // every tick up to the sleep fired, and none that was not yet due
+    assert!(fired >= 3);
+    assert!(fired <= elapsed.as_millis() / 50);
+}
+
+#[test]
+fn ticker_skip() {
+    let start = Instant::now();
+    let ticker = timer::Ticker::with_missed_tick(Duration::from_millis(50),
+                                                 timer::MissedTick::Skip);
+    thread::sleep(Duration::from_millis(175));
+    assert!(!ticker.signal().is_pending());
+
+    // the missed ticks were merged, so the next one is only due on the
+    // next multiple of the period after the one that fired
+    let next = ticker.signal();
+    let elapsed = start.elapsed();
+    if !next.is_pending() {
+        assert!(elapsed >= Duration::from_millis(200));
+    }
+}
+
+#[test]
+fn ticker_delay() {
+    let ticker = timer::Ticker::with_missed_tick(Duration::from_millis(50),
+                                                 timer::MissedTick::Delay);
+    thread::sleep(Duration::from_millis(175));
+    assert!(!ticker.signal().is_pending());
+    let start = Instant::now();
+    ticker.signal().wait().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(40));
+}
@@ -0,0 +1,146 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+#![cfg(feature = "tracing")]
+
+extern crate pulse;
+extern crate tracing;
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use pulse::*;
+
+/// Records every event and new span as `message key=value ...`
+#[derive(Clone, Default)]
+struct Recorder {
+    lines: Arc<Mutex<Vec<String>>>,
+    next: Arc<AtomicU64>,
+}
+
+struct Line(String);
+
+impl Visit for Line {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+impl Recorder {
+    fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let mut line = Line(String::new());
+        span.record(&mut line);
+        line.0.insert_str(0, span.metadata().name());
+        self.lines.lock().unwrap().push(line.0);
+        Id::from_u64(self.next.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let mut line = Line(String::new());
+        event.record(&mut line);
+        self.lines.lock().unwrap().push(line.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn pulse_events() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let (s, p) = Signal::named("job");
+        p.pulse();
+        s.wait().unwrap();
+    });
+
+    let lines = recorder.lines();
+    let id = |line: &str| line.split(' ').find(|f| f.starts_with("id=")).map(String::from);
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("signal created"));
+    assert!(lines[0].contains("name=\"job\""));
+    assert!(lines[1].starts_with("pulsed"));
+    assert!(id(&lines[0]).is_some());
+    assert_eq!(id(&lines[0]), id(&lines[1]));
+}
+
+#[test]
+fn drop_with_error() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let (s, p) = Signal::new();
+        p.fail(7);
+        assert_eq!(s.wait(), Err(WaitError::Failed(7)));
+    });
+
+    let lines = recorder.lines();
+    assert!(lines.iter().any(|l| l.starts_with("pulse dropped") && l.contains("state=Failed(7)")));
+}
+
+#[test]
+fn wait_span() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let (s, p) = Signal::new();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            p.pulse();
+        });
+        s.wait().unwrap();
+        t.join().unwrap();
+    });
+
+    let lines = recorder.lines();
+    assert!(lines.iter().any(|l| l.starts_with("wait id=")));
+    assert!(lines.iter().any(|l| l.starts_with("wait started")));
+    assert!(lines.iter().any(|l| l.starts_with("wait finished") && l.contains("Ok(())")));
+}
+
+#[test]
+fn select_ready() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut select = Select::new();
+        let (s, p) = Signal::named("selected");
+        select.add(s);
+        p.pulse();
+        assert!(select.next().is_some());
+    });
+
+    let lines = recorder.lines();
+    assert!(lines.iter().any(|l| l.starts_with("select ready") && l.contains("name=\"selected\"")));
+}
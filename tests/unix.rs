@@ -0,0 +1,69 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+#![cfg(unix)]
+
+extern crate libc;
+extern crate pulse;
+
+use std::time::Duration;
+use pulse::*;
+use pulse::unix::*;
+
+// Signals are process wide and the tests run in parallel, so every
+// test uses a different signal.
+
+fn raise(signum: libc::c_int) {
+    assert_eq!(unsafe { libc::raise(signum) }, 0);
+}
+
+#[test]
+fn once() {
+    let s = on_signal(SIGUSR1).unwrap();
+    assert!(s.is_pending());
+    raise(SIGUSR1);
+    s.wait().unwrap();
+}
+
+#[test]
+fn listener() {
+    let listener = SignalListener::new(SIGUSR2).unwrap();
+    assert_eq!(listener.signum(), SIGUSR2);
+
+    let s = listener.signal();
+    raise(SIGUSR2);
+    s.wait().unwrap();
+
+    raise(SIGUSR2);
+    listener.wait().unwrap();
+}
+
+#[test]
+fn select_map() {
+    let listener = SignalListener::new(SIGWINCH).unwrap();
+    let mut select = SelectMap::new();
+    select.add(timer::after(Duration::from_secs(60)), "timeout");
+    select.add(listener.signal(), "signal");
+    raise(SIGWINCH);
+
+    let (_, value) = select.next().unwrap();
+    assert_eq!(value, "signal");
+}
+
+#[test]
+fn invalid() {
+    assert!(on_signal(libc::SIGKILL).is_err());
+    assert!(SignalListener::new(libc::SIGSTOP).is_err());
+}